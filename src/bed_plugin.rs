@@ -0,0 +1,196 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_debug_text_overlay::screen_print;
+use bevy_egui::{egui, EguiContexts};
+use bevy_enum_filter::prelude::*;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::construction_plugin::{Structure, StructureRegistry};
+use crate::health_plugin::Collapsed;
+use crate::name_plugin::Name;
+use crate::pathing::Pos;
+use crate::reservation_plugin::{renew_claims, Claims, Reservations, Target};
+use crate::task_scorer::Busy;
+use crate::tasks::*;
+use crate::wander_plugin::{NeedsPath, PathFailed};
+use crate::world_gen_plugin::StructureKind;
+
+// how well anyone rests without a bed, compared to a bed's comfort
+pub const GROUND_SLEEP_QUALITY: f32 = 0.6;
+
+// a finished bed
+#[derive(Component)]
+pub struct Bed {
+    pub comfort: f32,
+}
+
+// on a colonist, the bed that's theirs
+#[derive(Component)]
+pub struct OwnedBed(pub Entity);
+
+// where a tired colonist is going to lie down
+#[derive(Component)]
+pub enum SleepSpot {
+    Bed { bed: Entity, pos: Pos },
+    // wherever they happen to be
+    Ground,
+}
+
+impl Claims for SleepSpot {
+    fn claimed(&self) -> Vec<Target> {
+        match self {
+            SleepSpot::Bed { bed, .. } => vec![Target::Entity(*bed)],
+            SleepSpot::Ground => vec![],
+        }
+    }
+}
+
+fn furnish_beds(
+    mut commands: Commands,
+    query: Query<(Entity, &Structure), Added<Structure>>,
+    registry: Res<StructureRegistry>,
+) {
+    for (entity, structure) in query.iter() {
+        let Some(def) = registry.get(&structure.def).filter(|def| def.kind == StructureKind::Bed) else {
+            continue;
+        };
+        commands.entity(entity).insert(Bed { comfort: def.comfort });
+    }
+}
+
+// their own bed if they have one, otherwise they claim the closest free bed nobody owns. With no bed to go
+// to they sleep where they stand
+fn choose_sleep_spot(
+    mut commands: Commands,
+    time: Res<Time>,
+    query: Query<
+        (Entity, &Transform, Option<&OwnedBed>, Option<&Name>),
+        (With<Character>, With<Enum!(AllTasks::Sleep)>, With<Busy>, Without<SleepSpot>, Without<Collapsed>),
+    >,
+    beds: Query<(Entity, &Structure), With<Bed>>,
+    owners: Query<&OwnedBed>,
+    mut reservations: ResMut<Reservations>,
+) {
+    let mut owned: HashSet<Entity> = owners.iter().map(|owned| owned.0).collect();
+
+    for (entity, transform, own_bed, name) in query.iter() {
+        let here = Pos::from_translation(transform.translation);
+        let free = |bed: Entity| !reservations.is_reserved(Target::Entity(bed), entity);
+
+        let bed = match own_bed {
+            Some(own) => beds.get(own.0).ok().filter(|(bed, _)| free(*bed)),
+            None => {
+                let unowned = beds
+                    .iter()
+                    .filter(|(bed, _)| !owned.contains(bed) && free(*bed))
+                    .min_by_key(|(_, structure)| structure.pos.distance(&here));
+                if let Some((bed, _)) = unowned {
+                    owned.insert(bed);
+                    commands.entity(entity).insert(OwnedBed(bed));
+                    if let Some(name) = name {
+                        screen_print!(push, sec: 3.0, "{} claimed a bed", &name.0);
+                    }
+                }
+                unowned
+            }
+        };
+
+        match bed {
+            Some((bed, structure)) => {
+                reservations.reserve(Target::Entity(bed), entity, AllTasks::Sleep, time.elapsed_seconds());
+                commands.entity(entity).insert((
+                    SleepSpot::Bed { bed, pos: structure.pos },
+                    NeedsPath { pos: structure.pos },
+                ));
+            }
+            None => {
+                commands.entity(entity).insert(SleepSpot::Ground);
+            }
+        }
+    }
+}
+
+// couldn't get to the bed, so the floor will have to do
+fn sleep_where_stuck(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut SleepSpot), With<PathFailed>>,
+    mut reservations: ResMut<Reservations>,
+) {
+    for (entity, mut spot) in query.iter_mut() {
+        if let SleepSpot::Bed { bed, .. } = *spot {
+            reservations.release(Target::Entity(bed), entity);
+        }
+        *spot = SleepSpot::Ground;
+        commands.entity(entity).remove::<PathFailed>();
+    }
+}
+
+// woke up early for something more important
+fn abandon_sleep(mut commands: Commands, query: Query<Entity, (With<SleepSpot>, Without<Enum!(AllTasks::Sleep)>)>) {
+    for entity in query.iter() {
+        commands.entity(entity).remove::<SleepSpot>();
+    }
+}
+
+// the bed was taken down
+fn forget_lost_beds(mut commands: Commands, query: Query<(Entity, &OwnedBed)>, beds: Query<(), With<Bed>>) {
+    for (entity, owned) in query.iter() {
+        if beds.get(owned.0).is_err() {
+            commands.entity(entity).remove::<OwnedBed>();
+        }
+    }
+}
+
+fn beds_ui(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    beds: Query<(Entity, &Structure), With<Bed>>,
+    colonists: Query<(Entity, &Name, Option<&OwnedBed>), With<Character>>,
+) {
+    egui::Window::new("Beds")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for (bed, structure) in beds.iter() {
+                let owner = colonists.iter().find(|(_, _, owned)| owned.is_some_and(|o| o.0 == bed));
+                let mut selected = owner.map(|(entity, _, _)| entity);
+
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} at {}, {}", structure.def, structure.pos.0, structure.pos.1));
+                    let text = owner.map_or("Nobody", |(_, name, _)| name.0.as_str());
+                    egui::ComboBox::from_id_source(bed).selected_text(text).show_ui(ui, |ui| {
+                        ui.selectable_value(&mut selected, None, "Nobody");
+                        for (entity, name, _) in colonists.iter() {
+                            ui.selectable_value(&mut selected, Some(entity), &name.0);
+                        }
+                    });
+                });
+
+                if selected == owner.map(|(entity, _, _)| entity) {
+                    continue;
+                }
+                if let Some((previous, _, _)) = owner {
+                    commands.entity(previous).remove::<OwnedBed>();
+                }
+                // replaces whatever bed they had before
+                if let Some(colonist) = selected {
+                    commands.entity(colonist).insert(OwnedBed(bed));
+                }
+            }
+        });
+}
+
+pub struct BedPlugin;
+
+impl Plugin for BedPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, furnish_beds.run_if(in_state(InGame)))
+            .add_systems(Update, choose_sleep_spot.run_if(in_state(InGame)))
+            .add_systems(Update, sleep_where_stuck.run_if(in_state(InGame)))
+            .add_systems(Update, renew_claims::<SleepSpot>.run_if(in_state(InGame)))
+            .add_systems(Update, abandon_sleep.run_if(in_state(InGame)))
+            .add_systems(Update, forget_lost_beds.run_if(in_state(InGame)))
+            .add_systems(Update, beds_ui.run_if(in_state(InGame)));
+    }
+}
@@ -5,8 +5,19 @@ use bevy::render::texture::ImageSampler;
 use rand::{Rng, thread_rng};
 
 use crate::{AppState, CharacterFolder};
+use crate::decision_log_plugin::DecisionLog;
+use crate::health_plugin::Health;
+use crate::item_plugin::Inventory;
+use crate::mood_plugin::{Mood, Thoughts};
+use crate::schedule_plugin::Schedule;
+use crate::skills::Skills;
+use crate::temperature_plugin::{ComfortRange, Exposure};
+use crate::social_plugin::Relationships;
+use crate::traits_plugin::{TraitRegistry, Traits};
+use crate::wander_plugin::MoveSpeed;
 use crate::name_plugin::NeedsName;
 use crate::tasks::*;
+use crate::work_plugin::WorkPriorities;
 
 #[derive(Component)]
 pub struct Character;
@@ -18,6 +29,20 @@ struct PlayerBundle {
     thirst: Thirst,
     hunger: Hunger,
     sleep: Sleep,
+    social: Social,
+    relationships: Relationships,
+    health: Health,
+    comfort_range: ComfortRange,
+    exposure: Exposure,
+    mood: Mood,
+    thoughts: Thoughts,
+    skills: Skills,
+    traits: Traits,
+    move_speed: MoveSpeed,
+    inventory: Inventory,
+    work_priorities: WorkPriorities,
+    schedule: Schedule,
+    decision_log: DecisionLog,
     target_task: AllTasks,
 }
 
@@ -31,6 +56,7 @@ fn add_people(
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
     loaded_folders: Res<Assets<LoadedFolder>>,
     mut textures: ResMut<Assets<Image>>,
+    trait_registry: Res<TraitRegistry>,
 ) {
     let mut rand = thread_rng();
     let loaded_folder = loaded_folders.get(&character_sprite_handles.0).unwrap();
@@ -53,6 +79,16 @@ fn add_people(
             100.0,
         );
 
+        let traits = trait_registry.roll(&mut rand);
+        let thirst = Thirst::default();
+        let hunger = Hunger::default();
+        let sleep = Sleep::default();
+        let schedule = if trait_registry.defs(&traits).any(|t| t.nocturnal) {
+            Schedule::nocturnal()
+        } else {
+            Schedule::default()
+        };
+
         //println!("Spawning at: {:?}", transform);
         commands
             .spawn((
@@ -63,9 +99,32 @@ fn add_people(
                         ..default()
                     },
                     character: Character,
-                    thirst: Thirst::default(),
-                    hunger: Hunger::default(),
-                    sleep: Sleep::default(),
+                    thirst: Thirst {
+                        drain_rate: thirst.drain_rate * trait_registry.product(&traits, |t| t.thirst_drain),
+                        ..thirst
+                    },
+                    hunger: Hunger {
+                        drain_rate: hunger.drain_rate * trait_registry.product(&traits, |t| t.hunger_drain),
+                        ..hunger
+                    },
+                    sleep: Sleep {
+                        drain_rate: sleep.drain_rate * trait_registry.product(&traits, |t| t.sleep_drain),
+                        ..sleep
+                    },
+                    move_speed: MoveSpeed(MoveSpeed::default().0 * trait_registry.product(&traits, |t| t.move_speed)),
+                    inventory: Inventory::default(),
+                    traits,
+                    social: Social::default(),
+                    relationships: Relationships::default(),
+                    health: Health::default(),
+                    comfort_range: ComfortRange::default(),
+                    exposure: Exposure::default(),
+                    mood: Mood::default(),
+                    thoughts: Thoughts::default(),
+                    skills: Skills::roll(&mut rand),
+                    work_priorities: WorkPriorities::default(),
+                    schedule,
+                    decision_log: DecisionLog::default(),
                     target_task: AllTasks::default(),
                 },
                 TextureAtlas {
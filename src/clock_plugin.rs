@@ -0,0 +1,183 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use strum_macros::{AsRefStr, EnumIter};
+
+use crate::AppState::InGame;
+use crate::world_gen_plugin::{SPRITE_SIZE, WORLD_SIZE_X, WORLD_SIZE_Y};
+
+// in-game minutes per real second, a full day takes 2.4 minutes
+pub const MINUTES_PER_SECOND: f32 = 10.0;
+pub const DAYS_PER_SEASON: u32 = 10;
+const STARTING_HOUR: u32 = 8;
+
+// the night is darkest between DUSK_END and DAWN_START, and fades in and out around them
+const DUSK_START: f32 = 18.0;
+const DUSK_END: f32 = 21.0;
+const DAWN_START: f32 = 5.0;
+const DAWN_END: f32 = 7.0;
+const MAX_DARKNESS: f32 = 0.6;
+
+// above the tilemap, below plants and colonists
+const NIGHT_TINT_Z: f32 = 50.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, EnumIter, AsRefStr)]
+pub enum Season {
+    #[default]
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub fn next(&self) -> Season {
+        match self {
+            Season::Spring => Season::Summer,
+            Season::Summer => Season::Autumn,
+            Season::Autumn => Season::Winter,
+            Season::Winter => Season::Spring,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct GameClock {
+    // minutes into the current minute, carried between ticks
+    pub fraction: f32,
+    pub minute: u32,
+    pub hour: u32,
+    // 1-based, within the season
+    pub day: u32,
+    pub season: Season,
+    // 1-based
+    pub year: u32,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        GameClock {
+            fraction: 0.0,
+            minute: 0,
+            hour: STARTING_HOUR,
+            day: 1,
+            season: Season::Spring,
+            year: 1,
+        }
+    }
+}
+
+impl GameClock {
+    pub fn advance(&mut self, minutes: f32) {
+        self.fraction += minutes;
+        while self.fraction >= 1.0 {
+            self.fraction -= 1.0;
+            self.minute += 1;
+            if self.minute < 60 {
+                continue;
+            }
+            self.minute = 0;
+            self.hour += 1;
+            if self.hour < 24 {
+                continue;
+            }
+            self.hour = 0;
+            self.day += 1;
+            if self.day <= DAYS_PER_SEASON {
+                continue;
+            }
+            self.day = 1;
+            self.season = self.season.next();
+            if self.season == Season::Spring {
+                self.year += 1;
+            }
+        }
+    }
+
+    // hour of the day with minutes as the fraction, 0.0 - 24.0
+    pub fn time_of_day(&self) -> f32 {
+        self.hour as f32 + (self.minute as f32 + self.fraction) / 60.0
+    }
+
+    // 0.0 during the day, 1.0 in the middle of the night
+    pub fn darkness(&self) -> f32 {
+        let hour = self.time_of_day();
+        if hour < DAWN_START || hour >= DUSK_END {
+            1.0
+        } else if hour < DAWN_END {
+            1.0 - (hour - DAWN_START) / (DAWN_END - DAWN_START)
+        } else if hour >= DUSK_START {
+            (hour - DUSK_START) / (DUSK_END - DUSK_START)
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Component)]
+struct NightTint;
+
+#[derive(Component)]
+struct ClockText;
+
+fn tick_clock(time: Res<Time>, mut clock: ResMut<GameClock>) {
+    clock.advance(time.delta_seconds() * MINUTES_PER_SECOND);
+}
+
+fn spawn_clock(mut commands: Commands) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.02, 0.02, 0.12, 0.0),
+                custom_size: Some(Vec2::new(
+                    (WORLD_SIZE_X * SPRITE_SIZE) as f32,
+                    (WORLD_SIZE_Y * SPRITE_SIZE) as f32,
+                )),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, NIGHT_TINT_Z),
+            ..default()
+        },
+        NightTint,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section("", TextStyle { font_size: 20.0, ..default() }).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            right: Val::Px(8.0),
+            ..default()
+        }),
+        ClockText,
+    ));
+}
+
+fn update_night_tint(clock: Res<GameClock>, mut query: Query<&mut Sprite, With<NightTint>>) {
+    for mut sprite in query.iter_mut() {
+        sprite.color.set_a(clock.darkness() * MAX_DARKNESS);
+    }
+}
+
+fn update_clock_text(clock: Res<GameClock>, mut query: Query<&mut Text, With<ClockText>>) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!(
+            "{:02}:{:02}  Day {} of {}, year {}",
+            clock.hour,
+            clock.minute,
+            clock.day,
+            clock.season.as_ref(),
+            clock.year
+        );
+    }
+}
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameClock>()
+            .add_systems(OnEnter(InGame), spawn_clock)
+            .add_systems(Update, tick_clock.run_if(in_state(InGame)))
+            .add_systems(Update, update_night_tint.run_if(in_state(InGame)))
+            .add_systems(Update, update_clock_text.run_if(in_state(InGame)));
+    }
+}
@@ -0,0 +1,535 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_debug_text_overlay::screen_print;
+use bevy_egui::{egui, EguiContexts};
+use bevy_enum_filter::prelude::*;
+use serde::Deserialize;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::growth_plugin::PlantTiles;
+use crate::input_plugin::{ClearZone, DesignateZone, TileRect, Tool};
+use crate::item_plugin::{drop_items, Carried, ConsumeItem, DropItem, Inventory, Item, PickUpItem, SpawnItem};
+use crate::mood_plugin::Mood;
+use crate::name_plugin::Name;
+use crate::pathing::{Pos, IMPASSABLE};
+use crate::reservation_plugin::{renew_claims, Claims, Reservations, Target};
+use crate::skills::{SkillType, Skills};
+use crate::task_scorer::Busy;
+use crate::tasks::*;
+use crate::traits_plugin::{TraitRegistry, Traits};
+use crate::wander_plugin::{NeedsPath, Path, PathFailed, PathPending};
+use crate::work_plugin::{AvailableWork, WorkType};
+use crate::world_gen_plugin::{EditTile, StructureKind, TerrainMap, TileWeights, SPRITE_SIZE};
+
+const STRUCTURE_DATA: &str = include_str!("../assets/data/structures.json");
+
+// floors go under the plants, walls and doors over them, blueprints over everything on the ground
+const FLOOR_Z: f32 = 35.0;
+const WALL_Z: f32 = 42.0;
+const BLUEPRINT_Z: f32 = 48.0;
+
+// how close the builder has to be to hand over materials or work on a blueprint
+const WORK_DISTANCE: f32 = 1.5 * SPRITE_SIZE as f32;
+const BUILD_XP: f32 = 10.0;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MaterialCost {
+    pub item: String,
+    pub count: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StructureDef {
+    pub id: String,
+    pub name: String,
+    pub kind: StructureKind,
+    pub materials: Vec<MaterialCost>,
+    // seconds of work at normal speed, once the materials are there
+    pub work: f32,
+    pub color: [f32; 3],
+    // how well colonists sleep in it, for beds. 1.0 is a plain bed
+    #[serde(default)]
+    pub comfort: f32,
+    // degrees it warms the tiles around it by, and how far out in tiles, for stoves and fires
+    #[serde(default)]
+    pub heat: f32,
+    #[serde(default)]
+    pub heat_radius: i32,
+}
+
+impl StructureDef {
+    fn z(&self) -> f32 {
+        match self.kind {
+            StructureKind::Floor => FLOOR_Z,
+            StructureKind::Wall | StructureKind::Door | StructureKind::Bed | StructureKind::Workbench | StructureKind::Heater => WALL_Z,
+        }
+    }
+
+    fn sprite(&self, pos: Pos, alpha: f32, z: f32) -> SpriteBundle {
+        let [r, g, b] = self.color;
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(r, g, b, alpha),
+                custom_size: Some(Vec2::splat(SPRITE_SIZE as f32)),
+                ..default()
+            },
+            transform: Transform::from_xyz((pos.0 * SPRITE_SIZE) as f32, (pos.1 * SPRITE_SIZE) as f32, z),
+            ..default()
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct StructureRegistry {
+    // in the order they're listed in the data, for the picker
+    pub defs: Vec<StructureDef>,
+}
+
+impl StructureRegistry {
+    pub fn get(&self, id: &str) -> Option<&StructureDef> {
+        self.defs.iter().find(|def| def.id == id)
+    }
+}
+
+// what new blueprints are for
+#[derive(Resource, Default)]
+pub struct SelectedStructure(pub String);
+
+// something the player wants built, and what has been brought for it so far
+#[derive(Component)]
+pub struct Blueprint {
+    pub def: String,
+    pub pos: Pos,
+    pub delivered: HashMap<String, u32>,
+    pub progress: f32,
+}
+
+impl Blueprint {
+    // how many more of `item` it needs
+    fn needs(&self, def: &StructureDef, item: &str) -> u32 {
+        def.materials
+            .iter()
+            .filter(|cost| cost.item == item)
+            .map(|cost| cost.count.saturating_sub(*self.delivered.get(item).unwrap_or(&0)))
+            .sum()
+    }
+
+    fn supplied(&self, def: &StructureDef) -> bool {
+        def.materials.iter().all(|cost| self.needs(def, &cost.item) == 0)
+    }
+}
+
+// a finished wall, floor or door
+#[derive(Component)]
+pub struct Structure {
+    pub def: String,
+    pub pos: Pos,
+}
+
+// the blueprint a builder is working on, and the materials they're on their way to pick up
+#[derive(Component)]
+pub struct BuildJob {
+    pub blueprint: Entity,
+    pub fetching: Option<Entity>,
+}
+
+impl Claims for BuildJob {
+    fn claimed(&self) -> Vec<Target> {
+        [Some(self.blueprint), self.fetching].into_iter().flatten().map(Target::Entity).collect()
+    }
+}
+
+fn load_structure_registry(mut commands: Commands) {
+    let defs: Vec<StructureDef> = serde_json::from_str(STRUCTURE_DATA).expect("invalid structure data");
+    let selected = defs.first().map(|def| def.id.clone()).unwrap_or_default();
+    commands.insert_resource(StructureRegistry { defs });
+    commands.insert_resource(SelectedStructure(selected));
+}
+
+// walls go round the edge of the dragged rectangle, so dragging out a room walls it in. Everything else
+// fills it
+fn blueprint_tiles(rect: TileRect, kind: StructureKind) -> Vec<Pos> {
+    rect.tiles()
+        .filter(|pos| {
+            kind != StructureKind::Wall
+                || pos.0 == rect.min.0
+                || pos.0 == rect.max.0
+                || pos.1 == rect.min.1
+                || pos.1 == rect.max.1
+        })
+        .collect()
+}
+
+fn refund(materials: impl Iterator<Item = (String, u32)>, pos: Pos, items: &mut EventWriter<SpawnItem>) {
+    for (def, count) in materials.filter(|(_, count)| *count > 0) {
+        items.send(SpawnItem { def, count, pos });
+    }
+}
+
+// right clicking a blueprint cancels it, right clicking something built takes it down. Either way the
+// materials are given back
+fn designate_blueprints(
+    mut commands: Commands,
+    mut designated: EventReader<DesignateZone>,
+    mut cleared: EventReader<ClearZone>,
+    registry: Res<StructureRegistry>,
+    selected: Res<SelectedStructure>,
+    terrain: Res<TerrainMap>,
+    blueprints: Query<(Entity, &Blueprint)>,
+    structures: Query<(Entity, &Structure)>,
+    mut edits: EventWriter<EditTile>,
+    mut items: EventWriter<SpawnItem>,
+) {
+    for event in designated.read() {
+        if event.tool != Tool::Build {
+            continue;
+        }
+        let Some(def) = registry.get(&selected.0) else {
+            continue;
+        };
+        for pos in blueprint_tiles(event.rect, def.kind) {
+            let planned = blueprints
+                .iter()
+                .filter(|(_, b)| b.pos == pos)
+                .filter_map(|(_, b)| registry.get(&b.def).map(|def| def.kind));
+            let taken = terrain.built(pos).chain(planned).any(|kind| !def.kind.fits_with(kind));
+            if taken || !terrain.tiles.contains_key(&pos) {
+                continue;
+            }
+            commands.spawn((
+                def.sprite(pos, 0.35, BLUEPRINT_Z),
+                Blueprint {
+                    def: def.id.clone(),
+                    pos,
+                    delivered: HashMap::new(),
+                    progress: 0.0,
+                },
+            ));
+        }
+    }
+
+    for event in cleared.read() {
+        if event.tool != Tool::Build {
+            continue;
+        }
+        for (entity, blueprint) in blueprints.iter().filter(|(_, b)| b.pos == event.pos) {
+            refund(blueprint.delivered.clone().into_iter(), event.pos, &mut items);
+            commands.entity(entity).despawn_recursive();
+        }
+        for (entity, structure) in structures.iter().filter(|(_, s)| s.pos == event.pos) {
+            if let Some(def) = registry.get(&structure.def) {
+                refund(def.materials.iter().map(|cost| (cost.item.clone(), cost.count)), event.pos, &mut items);
+                edits.send(EditTile {
+                    pos: event.pos,
+                    structure: def.kind,
+                    removed: true,
+                });
+            }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// a blueprint is worth taking if nobody else has it, and either it has everything it needs or there's
+// something lying around that it still needs
+fn workable(
+    blueprint: &Blueprint,
+    def: &StructureDef,
+    items: &Query<(Entity, &Item, &Pos)>,
+    reservations: &Reservations,
+) -> bool {
+    blueprint.supplied(def)
+        || items.iter().any(|(entity, item, _)| {
+            blueprint.needs(def, &item.def) > 0 && reservations.is_free(Target::Entity(entity))
+        })
+}
+
+pub fn find_build_work(
+    blueprints: Query<(Entity, &Blueprint)>,
+    items: Query<(Entity, &Item, &Pos)>,
+    registry: Res<StructureRegistry>,
+    reservations: Res<Reservations>,
+    mut available_work: ResMut<AvailableWork>,
+) {
+    let any = blueprints.iter().any(|(entity, blueprint)| {
+        reservations.is_free(Target::Entity(entity))
+            && registry.get(&blueprint.def).is_some_and(|def| workable(blueprint, def, &items, &reservations))
+    });
+    let tasks = if any { vec![AllTasks::Build] } else { vec![] };
+    available_work.jobs.insert(WorkType::Building, tasks);
+}
+
+// builders go for the closest blueprint they can make progress on
+fn take_build_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    query: Query<(Entity, &Transform), (With<Character>, With<Enum!(AllTasks::Build)>, With<Busy>, Without<BuildJob>)>,
+    blueprints: Query<(Entity, &Blueprint)>,
+    items: Query<(Entity, &Item, &Pos)>,
+    registry: Res<StructureRegistry>,
+    mut reservations: ResMut<Reservations>,
+) {
+    for (entity, transform) in query.iter() {
+        let here = Pos::from_translation(transform.translation);
+        let target = blueprints
+            .iter()
+            .filter(|(bp, _)| reservations.is_free(Target::Entity(*bp)))
+            .filter(|(_, b)| registry.get(&b.def).is_some_and(|def| workable(b, def, &items, &reservations)))
+            .min_by_key(|(_, b)| b.pos.distance(&here));
+
+        match target {
+            Some((blueprint, _)) => {
+                reservations.reserve(Target::Entity(blueprint), entity, AllTasks::Build, time.elapsed_seconds());
+                commands.entity(entity).insert(BuildJob { blueprint, fetching: None });
+            }
+            // someone else got there first
+            None => {
+                commands.entity(entity).remove::<Busy>();
+            }
+        }
+    }
+}
+
+pub fn within_reach(transform: &Transform, pos: Pos) -> bool {
+    let target = Vec2::new((pos.0 * SPRITE_SIZE) as f32, (pos.1 * SPRITE_SIZE) as f32);
+    transform.translation.truncate().distance(target) <= WORK_DISTANCE
+}
+
+// builders work from next to the blueprint, so nobody ends up inside the wall they just put up
+fn beside(pos: Pos, from: Pos, weights: &TileWeights) -> Pos {
+    (-1..=1)
+        .flat_map(|x| (-1..=1).map(move |y| Pos(pos.0 + x, pos.1 + y)))
+        .filter(|p| *p != pos && weights.weights.get(p).is_some_and(|w| *w != IMPASSABLE))
+        .min_by_key(|p| p.distance(&from))
+        .unwrap_or(pos)
+}
+
+// fetches whatever the blueprint is missing, one load at a time, then builds it
+fn do_build_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<
+        (Entity, &Transform, &mut BuildJob, &Inventory, Option<&PathFailed>, &mut Skills, &Mood, &Traits, Option<&Name>),
+        (With<Enum!(AllTasks::Build)>, Without<Path>, Without<NeedsPath>, Without<PathPending>),
+    >,
+    mut blueprints: Query<&mut Blueprint>,
+    ground: Query<(Entity, &Item, &Pos)>,
+    carried: Query<&Item, With<Carried>>,
+    registry: Res<StructureRegistry>,
+    trait_registry: Res<TraitRegistry>,
+    weights: Res<TileWeights>,
+    mut reservations: ResMut<Reservations>,
+    mut pick_ups: EventWriter<PickUpItem>,
+    mut consume: EventWriter<ConsumeItem>,
+    mut drops: EventWriter<DropItem>,
+    mut done: EventWriter<BlueprintDone>,
+) {
+    for (entity, transform, mut job, inventory, path_failed, mut skills, mood, traits, name) in query.iter_mut() {
+        let here = Pos::from_translation(transform.translation);
+
+        if path_failed.is_some() {
+            // either whatever they were fetching, or the blueprint itself
+            let target = job.fetching.unwrap_or(job.blueprint);
+            reservations.mark_unreachable(Target::Entity(target), time.elapsed_seconds());
+            inventory.drop_all(here, &mut drops);
+            commands.entity(entity).remove::<(BuildJob, PathFailed, Busy)>();
+            continue;
+        }
+        // cancelled while they were on their way
+        let Some((mut blueprint, def)) = blueprints
+            .get_mut(job.blueprint)
+            .ok()
+            .and_then(|b| registry.get(&b.def).map(|def| (b, def)))
+        else {
+            inventory.drop_all(here, &mut drops);
+            commands.entity(entity).remove::<(BuildJob, Busy)>();
+            continue;
+        };
+        let site = beside(blueprint.pos, here, &weights);
+
+        if !blueprint.supplied(def) {
+            if !inventory.is_empty() {
+                if !within_reach(transform, blueprint.pos) {
+                    commands.entity(entity).insert(NeedsPath { pos: site });
+                    continue;
+                }
+                // hands over what's needed, and puts down anything else they have on them
+                for carried_item in inventory.items.iter() {
+                    let Ok(item) = carried.get(*carried_item) else {
+                        continue;
+                    };
+                    let count = blueprint.needs(def, &item.def).min(item.count);
+                    if count > 0 {
+                        consume.send(ConsumeItem { item: *carried_item, count });
+                        *blueprint.delivered.entry(item.def.clone()).or_insert(0) += count;
+                    } else {
+                        drops.send(DropItem { item: *carried_item, pos: here });
+                    }
+                }
+                continue;
+            }
+
+            let fetching = job.fetching.and_then(|item| ground.get(item).ok());
+            if let Some((item, stack, pos)) = fetching {
+                if within_reach(transform, *pos) {
+                    pick_ups.send(PickUpItem {
+                        item,
+                        by: entity,
+                        count: blueprint.needs(def, &stack.def),
+                    });
+                    // whatever's left of the stack is free for anyone else who needs it
+                    reservations.release(Target::Entity(item), entity);
+                    job.fetching = None;
+                    commands.entity(entity).insert(NeedsPath { pos: site });
+                } else {
+                    commands.entity(entity).insert(NeedsPath { pos: *pos });
+                }
+                continue;
+            }
+
+            let nearest = ground
+                .iter()
+                .filter(|(item, stack, _)| blueprint.needs(def, &stack.def) > 0 && reservations.is_free(Target::Entity(*item)))
+                .min_by_key(|(_, _, pos)| pos.distance(&here));
+            match nearest {
+                Some((item, _, pos)) => {
+                    reservations.reserve(Target::Entity(item), entity, AllTasks::Build, time.elapsed_seconds());
+                    job.fetching = Some(item);
+                    commands.entity(entity).insert(NeedsPath { pos: *pos });
+                }
+                // nothing left to build it with
+                None => {
+                    commands.entity(entity).remove::<(BuildJob, Busy)>();
+                }
+            }
+            continue;
+        }
+
+        // whatever was left over from the deliveries
+        if !inventory.is_empty() {
+            inventory.drop_all(here, &mut drops);
+        }
+        if !within_reach(transform, blueprint.pos) {
+            commands.entity(entity).insert(NeedsPath { pos: site });
+            continue;
+        }
+
+        let speed = skills.work_speed(SkillType::Construction) * mood.work_speed() * trait_registry.product(traits, |d| d.work_speed);
+        blueprint.progress += speed * time.delta_seconds();
+        if blueprint.progress < def.work {
+            continue;
+        }
+
+        done.send(BlueprintDone { blueprint: job.blueprint });
+        commands.entity(entity).remove::<(BuildJob, Busy)>();
+
+        if skills.learn(SkillType::Construction, BUILD_XP) {
+            if let Some(name) = name {
+                screen_print!(push, sec: 3.0, "{} got better at Construction", &name.0);
+            }
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct BlueprintDone {
+    pub blueprint: Entity,
+}
+
+// swaps the blueprint for the real thing, clearing out any plant in the way
+fn finish_blueprints(
+    mut commands: Commands,
+    mut done: EventReader<BlueprintDone>,
+    blueprints: Query<&Blueprint>,
+    registry: Res<StructureRegistry>,
+    plant_tiles: Res<PlantTiles>,
+    mut edits: EventWriter<EditTile>,
+) {
+    for event in done.read() {
+        let Ok(blueprint) = blueprints.get(event.blueprint) else {
+            continue;
+        };
+        let Some(def) = registry.get(&blueprint.def) else {
+            continue;
+        };
+        commands.entity(event.blueprint).despawn_recursive();
+        if let Some(plant) = plant_tiles.plants.get(&blueprint.pos) {
+            commands.entity(*plant).despawn_recursive();
+        }
+
+        commands.spawn((
+            def.sprite(blueprint.pos, 1.0, def.z()),
+            Structure {
+                def: def.id.clone(),
+                pos: blueprint.pos,
+            },
+        ));
+        edits.send(EditTile {
+            pos: blueprint.pos,
+            structure: def.kind,
+            removed: false,
+        });
+    }
+}
+
+// the builder went off to do something more important, and puts down whatever they were bringing
+fn abandon_build_jobs(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &Inventory), (With<BuildJob>, Without<Enum!(AllTasks::Build)>)>,
+    mut drops: EventWriter<DropItem>,
+) {
+    for (entity, transform, inventory) in query.iter() {
+        inventory.drop_all(Pos::from_translation(transform.translation), &mut drops);
+        commands.entity(entity).remove::<BuildJob>();
+    }
+}
+
+fn construction_ui(
+    mut contexts: EguiContexts,
+    registry: Res<StructureRegistry>,
+    mut selected: ResMut<SelectedStructure>,
+    blueprints: Query<&Blueprint>,
+) {
+    egui::Window::new("Construction")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Build");
+                let current = registry.get(&selected.0).map(|def| def.name.as_str()).unwrap_or("");
+                egui::ComboBox::from_id_source("structure").selected_text(current).show_ui(ui, |ui| {
+                    for def in registry.defs.iter() {
+                        ui.selectable_value(&mut selected.0, def.id.clone(), &def.name);
+                    }
+                });
+            });
+            if let Some(def) = registry.get(&selected.0) {
+                let cost: Vec<String> = def.materials.iter().map(|cost| format!("{} {}", cost.count, cost.item)).collect();
+                ui.label(format!("Needs {} per tile", cost.join(", ")));
+            }
+            ui.separator();
+
+            let waiting = blueprints.iter().filter(|b| registry.get(&b.def).is_some_and(|def| !b.supplied(def))).count();
+            ui.label(format!("{} blueprints, {} waiting for materials", blueprints.iter().count(), waiting));
+        });
+}
+
+pub struct ConstructionPlugin;
+
+impl Plugin for ConstructionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BlueprintDone>()
+            .add_systems(Startup, load_structure_registry)
+            .add_systems(Update, designate_blueprints.run_if(in_state(InGame)))
+            .add_systems(Update, find_build_work.after(designate_blueprints).run_if(in_state(InGame)))
+            .add_systems(Update, take_build_jobs.after(find_build_work).run_if(in_state(InGame)))
+            // the items it hands over, picks up and puts down are all dealt with the same frame, so it
+            // never sees a half-delivered load
+            .add_systems(Update, do_build_jobs.after(take_build_jobs).before(drop_items).run_if(in_state(InGame)))
+            .add_systems(Update, finish_blueprints.after(do_build_jobs).run_if(in_state(InGame)))
+            .add_systems(Update, renew_claims::<BuildJob>.run_if(in_state(InGame)))
+            .add_systems(Update, abandon_build_jobs.run_if(in_state(InGame)))
+            .add_systems(Update, construction_ui.run_if(in_state(InGame)));
+    }
+}
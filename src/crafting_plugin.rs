@@ -0,0 +1,493 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_debug_text_overlay::screen_print;
+use bevy_egui::{egui, EguiContexts};
+use bevy_enum_filter::prelude::*;
+use serde::Deserialize;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::construction_plugin::{find_build_work, within_reach, MaterialCost, Structure, StructureRegistry};
+use crate::farming_plugin::find_farm_work;
+use crate::item_plugin::{drop_items, Carried, ConsumeItem, DropItem, Inventory, Item, PickUpItem, SpawnItem};
+use crate::ledger_plugin::ColonyLedger;
+use crate::mood_plugin::Mood;
+use crate::name_plugin::Name;
+use crate::pathing::Pos;
+use crate::reservation_plugin::{renew_claims, Claims, Reservations, Target};
+use crate::skills::{SkillType, Skills};
+use crate::task_scorer::Busy;
+use crate::tasks::*;
+use crate::traits_plugin::{TraitRegistry, Traits};
+use crate::wander_plugin::{NeedsPath, Path, PathFailed, PathPending};
+use crate::work_plugin::{AvailableWork, WorkPriorities, WorkType};
+use crate::world_gen_plugin::StructureKind;
+
+const RECIPE_DATA: &str = include_str!("../assets/data/recipes.json");
+
+const CRAFT_XP: f32 = 8.0;
+// what the bill buttons start new bills at
+const DEFAULT_MAKE_COUNT: u32 = 10;
+const DEFAULT_TARGET_COUNT: u32 = 20;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Recipe {
+    pub id: String,
+    pub name: String,
+    // the structure id of the workbench it's made at
+    pub bench: String,
+    pub inputs: Vec<MaterialCost>,
+    pub outputs: Vec<MaterialCost>,
+    // seconds of work at normal speed, once the ingredients are at the bench
+    pub work: f32,
+    pub skill: SkillType,
+}
+
+impl Recipe {
+    // what "until you have" bills count
+    fn product(&self) -> Option<&str> {
+        self.outputs.first().map(|output| output.item.as_str())
+    }
+}
+
+#[derive(Resource)]
+pub struct RecipeRegistry {
+    pub recipes: Vec<Recipe>,
+}
+
+impl RecipeRegistry {
+    pub fn get(&self, id: &str) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.id == id)
+    }
+
+    pub fn for_bench<'a>(&'a self, bench: &'a str) -> impl Iterator<Item = &'a Recipe> {
+        self.recipes.iter().filter(move |recipe| recipe.bench == bench)
+    }
+}
+
+// the kind of work a recipe counts as in the work priorities
+fn work_type(skill: SkillType) -> Option<WorkType> {
+    match skill {
+        SkillType::Cooking => Some(WorkType::Cooking),
+        SkillType::Construction => Some(WorkType::Building),
+        SkillType::Farming => Some(WorkType::Growing),
+        SkillType::Mining => Some(WorkType::Mining),
+        SkillType::Social => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BillMode {
+    // this many more times, then the bill is done
+    Make(u32),
+    // whenever the colony has fewer than this many of the product
+    UntilYouHave(u32),
+}
+
+pub struct Bill {
+    // unique at its bench, so a crafter can tell if the bill they're working on is still there
+    pub id: u32,
+    pub recipe: String,
+    pub mode: BillMode,
+}
+
+impl Bill {
+    fn wanted(&self, recipe: &Recipe, ledger: &ColonyLedger) -> bool {
+        match self.mode {
+            BillMode::Make(count) => count > 0,
+            BillMode::UntilYouHave(target) => recipe.product().is_some_and(|item| ledger.total(item) < target),
+        }
+    }
+}
+
+// a finished workbench and the queue of things the player wants made at it, first bill first
+#[derive(Component, Default)]
+pub struct Workbench {
+    pub bills: Vec<Bill>,
+    next_id: u32,
+}
+
+impl Workbench {
+    pub fn add_bill(&mut self, recipe: &str, mode: BillMode) {
+        self.bills.push(Bill {
+            id: self.next_id,
+            recipe: recipe.to_string(),
+            mode,
+        });
+        self.next_id += 1;
+    }
+}
+
+// the bill and recipe a crafter is making, the ingredient they're on their way to pick up, and how far
+// along the work is
+#[derive(Component)]
+pub struct CraftJob {
+    pub bench: Entity,
+    pub bill: u32,
+    pub recipe: String,
+    pub fetching: Option<Entity>,
+    pub progress: f32,
+}
+
+impl Claims for CraftJob {
+    fn claimed(&self) -> Vec<Target> {
+        [Some(self.bench), self.fetching].into_iter().flatten().map(Target::Entity).collect()
+    }
+}
+
+fn load_recipe_registry(mut commands: Commands) {
+    let recipes: Vec<Recipe> = serde_json::from_str(RECIPE_DATA).expect("invalid recipe data");
+    commands.insert_resource(RecipeRegistry { recipes });
+}
+
+fn furnish_workbenches(
+    mut commands: Commands,
+    query: Query<(Entity, &Structure), Added<Structure>>,
+    registry: Res<StructureRegistry>,
+) {
+    for (entity, structure) in query.iter() {
+        if registry.get(&structure.def).is_some_and(|def| def.kind == StructureKind::Workbench) {
+            commands.entity(entity).insert(Workbench::default());
+        }
+    }
+}
+
+// enough of every ingredient is lying around with nobody going for it
+fn ingredients_free(recipe: &Recipe, ground: &Query<(Entity, &Item, &Pos)>, reservations: &Reservations) -> bool {
+    recipe.inputs.iter().all(|input| {
+        let free: u32 = ground
+            .iter()
+            .filter(|(entity, item, _)| item.def == input.item && reservations.is_free(Target::Entity(*entity)))
+            .map(|(_, item, _)| item.count)
+            .sum();
+        free >= input.count
+    })
+}
+
+// the first bill at `bench` that wants doing and can be, and that `allowed` lets the crafter take on
+fn next_bill<'a>(
+    bench: &Workbench,
+    recipes: &'a RecipeRegistry,
+    ledger: &ColonyLedger,
+    ground: &Query<(Entity, &Item, &Pos)>,
+    reservations: &Reservations,
+    allowed: impl Fn(WorkType) -> bool,
+) -> Option<(u32, &'a Recipe)> {
+    bench
+        .bills
+        .iter()
+        .filter_map(|bill| {
+            let recipe = recipes.get(&bill.recipe).filter(|recipe| bill.wanted(recipe, ledger))?;
+            Some((bill.id, recipe))
+        })
+        .filter(|(_, recipe)| work_type(recipe.skill).is_some_and(&allowed))
+        .find(|(_, recipe)| ingredients_free(recipe, ground, reservations))
+}
+
+fn find_craft_work(
+    benches: Query<(Entity, &Workbench)>,
+    ground: Query<(Entity, &Item, &Pos)>,
+    ledger: Res<ColonyLedger>,
+    recipes: Res<RecipeRegistry>,
+    reservations: Res<Reservations>,
+    mut available_work: ResMut<AvailableWork>,
+) {
+    let crafted: Vec<WorkType> = recipes.recipes.iter().filter_map(|recipe| work_type(recipe.skill)).collect();
+
+    // other plugins hand out work of the same types, so only our own entry is replaced
+    for work in crafted.iter() {
+        let offered = benches.iter().any(|(entity, bench)| {
+            reservations.is_free(Target::Entity(entity))
+                && next_bill(bench, &recipes, &ledger, &ground, &reservations, |w| w == *work).is_some()
+        });
+        let tasks = available_work.jobs.entry(*work).or_default();
+        tasks.retain(|task| *task != AllTasks::Craft);
+        if offered {
+            tasks.push(AllTasks::Craft);
+        }
+    }
+}
+
+// crafters go for the closest bench with a bill they'd do
+fn take_craft_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    query: Query<
+        (Entity, &Transform, &WorkPriorities),
+        (With<Character>, With<Enum!(AllTasks::Craft)>, With<Busy>, Without<CraftJob>),
+    >,
+    benches: Query<(Entity, &Workbench, &Structure)>,
+    ground: Query<(Entity, &Item, &Pos)>,
+    ledger: Res<ColonyLedger>,
+    recipes: Res<RecipeRegistry>,
+    mut reservations: ResMut<Reservations>,
+) {
+    for (entity, transform, priorities) in query.iter() {
+        let here = Pos::from_translation(transform.translation);
+        let allowed = |work: WorkType| priorities.get(work) > 0;
+        let target = benches
+            .iter()
+            .filter(|(bench, _, _)| reservations.is_free(Target::Entity(*bench)))
+            .filter_map(|(bench, workbench, structure)| {
+                next_bill(workbench, &recipes, &ledger, &ground, &reservations, allowed)
+                    .map(|(bill, recipe)| (bench, structure.pos, bill, recipe))
+            })
+            .min_by_key(|(_, pos, _, _)| pos.distance(&here));
+
+        match target {
+            Some((bench, _, bill, recipe)) => {
+                reservations.reserve(Target::Entity(bench), entity, AllTasks::Craft, time.elapsed_seconds());
+                commands.entity(entity).insert(CraftJob {
+                    bench,
+                    bill,
+                    recipe: recipe.id.clone(),
+                    fetching: None,
+                    progress: 0.0,
+                });
+            }
+            // someone else got there first
+            None => {
+                commands.entity(entity).remove::<Busy>();
+            }
+        }
+    }
+}
+
+// fetches the ingredients one stack at a time, carries them to the bench, then works them into the product
+fn do_craft_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<
+        (Entity, &Transform, &mut CraftJob, &Inventory, Option<&PathFailed>, &mut Skills, &Mood, &Traits, Option<&Name>),
+        (With<Enum!(AllTasks::Craft)>, Without<Path>, Without<NeedsPath>, Without<PathPending>),
+    >,
+    mut benches: Query<(&mut Workbench, &Structure)>,
+    ground: Query<(Entity, &Item, &Pos)>,
+    carried: Query<&Item, With<Carried>>,
+    recipes: Res<RecipeRegistry>,
+    trait_registry: Res<TraitRegistry>,
+    mut reservations: ResMut<Reservations>,
+    mut pick_ups: EventWriter<PickUpItem>,
+    mut consume: EventWriter<ConsumeItem>,
+    mut drops: EventWriter<DropItem>,
+    mut spawns: EventWriter<SpawnItem>,
+) {
+    for (entity, transform, mut job, inventory, path_failed, mut skills, mood, traits, name) in query.iter_mut() {
+        let here = Pos::from_translation(transform.translation);
+
+        if path_failed.is_some() {
+            // either whatever they were fetching, or the bench itself
+            let target = job.fetching.unwrap_or(job.bench);
+            reservations.mark_unreachable(Target::Entity(target), time.elapsed_seconds());
+            inventory.drop_all(here, &mut drops);
+            commands.entity(entity).remove::<(CraftJob, PathFailed, Busy)>();
+            continue;
+        }
+        // the bench was taken down
+        let (Ok((mut bench, structure)), Some(recipe)) = (benches.get_mut(job.bench), recipes.get(&job.recipe)) else {
+            inventory.drop_all(here, &mut drops);
+            commands.entity(entity).remove::<(CraftJob, Busy)>();
+            continue;
+        };
+        // the player cancelled the bill, or someone else finished it
+        if !bench.bills.iter().any(|bill| bill.id == job.bill) {
+            inventory.drop_all(here, &mut drops);
+            commands.entity(entity).remove::<(CraftJob, Busy)>();
+            continue;
+        }
+
+        let holding = |item: &str| -> u32 {
+            inventory
+                .items
+                .iter()
+                .filter_map(|carried_item| carried.get(*carried_item).ok())
+                .filter(|stack| stack.def == item)
+                .map(|stack| stack.count)
+                .sum()
+        };
+        let missing = |item: &str| -> u32 {
+            recipe
+                .inputs
+                .iter()
+                .filter(|input| input.item == item)
+                .map(|input| input.count.saturating_sub(holding(item)))
+                .sum()
+        };
+
+        if recipe.inputs.iter().any(|input| missing(&input.item) > 0) {
+            let fetching = job.fetching.and_then(|item| ground.get(item).ok());
+            if let Some((item, stack, pos)) = fetching {
+                if within_reach(transform, *pos) {
+                    pick_ups.send(PickUpItem {
+                        item,
+                        by: entity,
+                        count: missing(&stack.def),
+                    });
+                    // whatever's left of the stack is free for anyone else who needs it
+                    reservations.release(Target::Entity(item), entity);
+                    job.fetching = None;
+                } else {
+                    commands.entity(entity).insert(NeedsPath { pos: *pos });
+                }
+                continue;
+            }
+
+            let nearest = ground
+                .iter()
+                .filter(|(item, stack, _)| missing(&stack.def) > 0 && reservations.is_free(Target::Entity(*item)))
+                .min_by_key(|(_, _, pos)| pos.distance(&here));
+            match nearest {
+                Some((item, _, pos)) => {
+                    reservations.reserve(Target::Entity(item), entity, AllTasks::Craft, time.elapsed_seconds());
+                    job.fetching = Some(item);
+                    commands.entity(entity).insert(NeedsPath { pos: *pos });
+                }
+                // somebody ate the ingredients
+                None => {
+                    inventory.drop_all(here, &mut drops);
+                    commands.entity(entity).remove::<(CraftJob, Busy)>();
+                }
+            }
+            continue;
+        }
+
+        if !within_reach(transform, structure.pos) {
+            commands.entity(entity).insert(NeedsPath { pos: structure.pos });
+            continue;
+        }
+
+        let speed = skills.work_speed(recipe.skill) * mood.work_speed() * trait_registry.product(traits, |d| d.work_speed);
+        job.progress += speed * time.delta_seconds();
+        if job.progress < recipe.work {
+            continue;
+        }
+
+        // uses up the ingredients and puts down anything else they had on them
+        let mut needed: HashMap<&str, u32> = recipe.inputs.iter().map(|input| (input.item.as_str(), input.count)).collect();
+        for carried_item in inventory.items.iter() {
+            let Ok(stack) = carried.get(*carried_item) else {
+                continue;
+            };
+            let count = needed.get_mut(stack.def.as_str()).map_or(0, |left| {
+                let count = (*left).min(stack.count);
+                *left -= count;
+                count
+            });
+            if count > 0 {
+                consume.send(ConsumeItem { item: *carried_item, count });
+            }
+            if count < stack.count {
+                drops.send(DropItem { item: *carried_item, pos: here });
+            }
+        }
+        for output in recipe.outputs.iter() {
+            spawns.send(SpawnItem {
+                def: output.item.clone(),
+                count: output.count,
+                pos: structure.pos,
+            });
+        }
+
+        if let Some(BillMode::Make(count)) = bench.bills.iter_mut().find(|bill| bill.id == job.bill).map(|bill| &mut bill.mode) {
+            *count = count.saturating_sub(1);
+        }
+        bench.bills.retain(|bill| bill.mode != BillMode::Make(0));
+
+        commands.entity(entity).remove::<(CraftJob, Busy)>();
+        if let Some(name) = name {
+            screen_print!(push, sec: 3.0, "{}: {} done", &name.0, recipe.name);
+        }
+        if skills.learn(recipe.skill, CRAFT_XP) {
+            if let Some(name) = name {
+                screen_print!(push, sec: 3.0, "{} got better at {}", &name.0, recipe.skill.as_ref());
+            }
+        }
+    }
+}
+
+// the crafter went off to do something more important, and puts the ingredients down
+fn abandon_craft_jobs(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &Inventory), (With<CraftJob>, Without<Enum!(AllTasks::Craft)>)>,
+    mut drops: EventWriter<DropItem>,
+) {
+    for (entity, transform, inventory) in query.iter() {
+        inventory.drop_all(Pos::from_translation(transform.translation), &mut drops);
+        commands.entity(entity).remove::<CraftJob>();
+    }
+}
+
+fn workbench_ui(
+    mut contexts: EguiContexts,
+    mut benches: Query<(Entity, &mut Workbench, &Structure)>,
+    structures: Res<StructureRegistry>,
+    recipes: Res<RecipeRegistry>,
+) {
+    egui::Window::new("Workbenches")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for (entity, mut bench, structure) in benches.iter_mut() {
+                let bench_name = structures.get(&structure.def).map_or(structure.def.as_str(), |def| def.name.as_str());
+                ui.push_id(entity, |ui| {
+                    ui.label(format!("{} at {}, {}", bench_name, structure.pos.0, structure.pos.1));
+
+                    let mut removed = None;
+                    for (i, bill) in bench.bills.iter_mut().enumerate() {
+                        let recipe_name = recipes.get(&bill.recipe).map_or(bill.recipe.as_str(), |recipe| recipe.name.as_str());
+                        ui.horizontal(|ui| {
+                            let (text, count) = match &mut bill.mode {
+                                BillMode::Make(count) => (format!("{}: make {}", recipe_name, count), count),
+                                BillMode::UntilYouHave(count) => (format!("{}: until you have {}", recipe_name, count), count),
+                            };
+                            ui.label(text);
+                            if ui.button("-").clicked() {
+                                *count = count.saturating_sub(1).max(1);
+                            }
+                            if ui.button("+").clicked() {
+                                *count += 1;
+                            }
+                            if ui.button("x").clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                    }
+                    if let Some(i) = removed {
+                        bench.bills.remove(i);
+                    }
+
+                    for recipe in recipes.for_bench(&structure.def) {
+                        ui.horizontal(|ui| {
+                            let inputs: Vec<String> = recipe.inputs.iter().map(|input| format!("{} {}", input.count, input.item)).collect();
+                            ui.label(format!("{} ({})", recipe.name, inputs.join(", ")));
+                            if ui.button(format!("Make {}", DEFAULT_MAKE_COUNT)).clicked() {
+                                bench.add_bill(&recipe.id, BillMode::Make(DEFAULT_MAKE_COUNT));
+                            }
+                            if ui.button(format!("Until {}", DEFAULT_TARGET_COUNT)).clicked() {
+                                bench.add_bill(&recipe.id, BillMode::UntilYouHave(DEFAULT_TARGET_COUNT));
+                            }
+                        });
+                    }
+                });
+                ui.separator();
+            }
+        });
+}
+
+pub struct CraftingPlugin;
+
+impl Plugin for CraftingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, load_recipe_registry)
+            .add_systems(Update, furnish_workbenches.run_if(in_state(InGame)))
+            // building and growing replace their whole job lists, which would wipe out a Craft entry
+            // for a recipe of either kind put in before them
+            .add_systems(Update, find_craft_work.after(find_build_work).after(find_farm_work).run_if(in_state(InGame)))
+            .add_systems(Update, take_craft_jobs.after(find_craft_work).run_if(in_state(InGame)))
+            // like building, the ingredients it uses up and puts down are dealt with the same frame
+            .add_systems(Update, do_craft_jobs.after(take_craft_jobs).before(drop_items).run_if(in_state(InGame)))
+            .add_systems(Update, renew_claims::<CraftJob>.run_if(in_state(InGame)))
+            .add_systems(Update, abandon_craft_jobs.run_if(in_state(InGame)))
+            .add_systems(Update, workbench_ui.run_if(in_state(InGame)));
+    }
+}
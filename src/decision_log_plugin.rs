@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::fs;
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde::Serialize;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::name_plugin::Name;
+use crate::task_scorer::Candidate;
+use crate::tasks::AllTasks;
+
+// how many decisions each colonist remembers before the oldest ones are dropped
+pub const DECISION_LOG_SIZE: usize = 64;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Decision {
+    // seconds since startup of the first and most recent time this decision was made
+    pub first_time: f32,
+    pub last_time: f32,
+    pub repeats: u32,
+    pub chosen: AllTasks,
+    // sorted best first
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Component, Default)]
+pub struct DecisionLog {
+    pub decisions: VecDeque<Decision>,
+}
+
+impl DecisionLog {
+    pub fn record(&mut self, time: f32, candidates: Vec<Candidate>) {
+        let Some(chosen) = candidates.first().map(|c| c.task) else {
+            return;
+        };
+
+        // idle colonists are re-scored every frame, so identical back to back decisions are folded together
+        if let Some(last) = self.decisions.back_mut() {
+            if last.chosen == chosen && last.candidates == candidates {
+                last.last_time = time;
+                last.repeats += 1;
+                return;
+            }
+        }
+
+        if self.decisions.len() == DECISION_LOG_SIZE {
+            self.decisions.pop_front();
+        }
+        self.decisions.push_back(Decision {
+            first_time: time,
+            last_time: time,
+            repeats: 1,
+            chosen,
+            candidates,
+        });
+    }
+}
+
+#[derive(Serialize)]
+struct ExportedLog<'a> {
+    name: &'a str,
+    decisions: &'a VecDeque<Decision>,
+}
+
+fn export_logs(path: &str, logs: Vec<ExportedLog>) {
+    let json = match serde_json::to_string_pretty(&logs) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("Failed to serialize decision logs: {}", e);
+            return;
+        }
+    };
+
+    match fs::write(path, json) {
+        Ok(_) => info!("Exported decision logs to {}", path),
+        Err(e) => warn!("Failed to write {}: {}", path, e),
+    }
+}
+
+fn decision_log_ui(
+    mut contexts: EguiContexts,
+    mut selected: Local<Option<Entity>>,
+    query: Query<(Entity, &Name, &DecisionLog), With<Character>>,
+) {
+    egui::Window::new("Decision log")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let selected_name = selected
+                .and_then(|e| query.get(e).ok())
+                .map(|(_, name, _)| name.0.clone())
+                .unwrap_or("Select a colonist".to_string());
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("decision_log_colonist")
+                    .selected_text(selected_name)
+                    .show_ui(ui, |ui| {
+                        for (entity, name, _) in query.iter() {
+                            ui.selectable_value(&mut *selected, Some(entity), &name.0);
+                        }
+                    });
+
+                if let Some((entity, name, log)) = selected.and_then(|e| query.get(e).ok()) {
+                    if ui.button("Export JSON").clicked() {
+                        // names can repeat, the entity keeps two colonists' exports apart
+                        export_logs(
+                            &format!("decision_log_{}_{}.json", name.0, entity.index()),
+                            vec![ExportedLog { name: &name.0, decisions: &log.decisions }],
+                        );
+                    }
+                }
+
+                if ui.button("Export all").clicked() {
+                    export_logs(
+                        "decision_logs.json",
+                        query
+                            .iter()
+                            .map(|(_, name, log)| ExportedLog { name: &name.0, decisions: &log.decisions })
+                            .collect(),
+                    );
+                }
+            });
+
+            let Some((_, _, log)) = selected.and_then(|e| query.get(e).ok()) else {
+                return;
+            };
+
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                // newest first
+                for decision in log.decisions.iter().rev() {
+                    let header = format!(
+                        "{:.1}s  {} (x{})",
+                        decision.last_time,
+                        decision.chosen.as_ref(),
+                        decision.repeats
+                    );
+                    egui::CollapsingHeader::new(header)
+                        .id_source(decision.first_time.to_bits())
+                        .show(ui, |ui| {
+                            for candidate in decision.candidates.iter() {
+                                let breakdown: Vec<String> = candidate
+                                    .considerations
+                                    .iter()
+                                    .map(|c| format!("{} {:+.2}", c.name, c.value))
+                                    .collect();
+                                ui.label(format!(
+                                    "{}: {:.2} = {}",
+                                    candidate.task.as_ref(),
+                                    candidate.score,
+                                    breakdown.join(", ")
+                                ));
+                            }
+                        });
+                }
+            });
+        });
+}
+
+pub struct DecisionLogPlugin;
+
+impl Plugin for DecisionLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, decision_log_ui.run_if(in_state(InGame)));
+    }
+}
@@ -0,0 +1,395 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_debug_text_overlay::screen_print;
+use bevy_egui::{egui, EguiContexts};
+use rand::thread_rng;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::growth_plugin::{GrowthStage, PlacePlant, Plant, PlantRegistry, PlantSpecies, PlantTiles, Tended, TENDED_DURATION};
+use crate::input_plugin::{zone_sprite, ClearZone, DesignateZone, TileRect, Tool};
+use crate::item_plugin::SpawnItem;
+use crate::mood_plugin::Mood;
+use crate::name_plugin::Name;
+use crate::pathing::Pos;
+use crate::reservation_plugin::{renew_claims, Claims, Reservations, Target};
+use crate::skills::{SkillType, Skills};
+use crate::task_scorer::Busy;
+use crate::tasks::AllTasks;
+use crate::traits_plugin::{TraitRegistry, Traits};
+use crate::wander_plugin::{NeedsPath, Path, PathFailed, PathPending};
+use crate::work_plugin::{AvailableWork, WorkType};
+use crate::world_gen_plugin::{TerrainMap, SPRITE_SIZE};
+
+// the order farm work is offered in. Ripe crops first, so they don't wither in the field
+const FARM_TASKS: [AllTasks; 4] = [AllTasks::Harvest, AllTasks::Sow, AllTasks::Till, AllTasks::Tend];
+
+// how close the farmer has to stand to work a tile
+const WORK_DISTANCE: f32 = 1.5 * SPRITE_SIZE as f32;
+const FARM_XP: f32 = 10.0;
+
+// seconds of work at normal speed
+fn work_amount(task: AllTasks) -> f32 {
+    match task {
+        AllTasks::Till => 4.0,
+        AllTasks::Sow => 2.0,
+        AllTasks::Tend => 2.0,
+        AllTasks::Harvest => 3.0,
+        _ => 0.0,
+    }
+}
+
+// a rectangle of tiles where colonists keep one crop growing
+#[derive(Component)]
+pub struct GrowZone {
+    pub crop: String,
+    pub rect: TileRect,
+    pub tilled: HashSet<Pos>,
+}
+
+// the crop newly drawn zones are planted with
+#[derive(Resource, Default)]
+pub struct SelectedCrop(pub String);
+
+// every tile that needs farm work, by the task that does it. Rebuilt each frame
+#[derive(Resource, Default)]
+pub struct FarmWork {
+    pub targets: HashMap<AllTasks, Vec<(Pos, Entity)>>,
+}
+
+// the tile a farmer is working on, and how far along they are
+#[derive(Component)]
+pub struct FarmJob {
+    pub task: AllTasks,
+    pub pos: Pos,
+    pub zone: Entity,
+    pub progress: f32,
+}
+
+impl Claims for FarmJob {
+    fn claimed(&self) -> Vec<Target> {
+        vec![Target::Tile(self.pos)]
+    }
+}
+
+#[derive(Event)]
+pub struct FarmJobDone {
+    pub task: AllTasks,
+    pub pos: Pos,
+    pub zone: Entity,
+    // 0.0 - 1.0, from the farmer's skill
+    pub quality: f32,
+}
+
+// species that give something when harvested, sorted so the crop picker doesn't jump around
+fn farmable_crops(registry: &PlantRegistry) -> Vec<&PlantSpecies> {
+    let mut crops: Vec<&PlantSpecies> = registry.species.values().filter(|s| s.harvest.is_some()).collect();
+    crops.sort_by(|a, b| a.name.cmp(&b.name));
+    crops
+}
+
+fn pick_default_crop(registry: Res<PlantRegistry>, mut selected: ResMut<SelectedCrop>) {
+    if let Some(crop) = farmable_crops(&registry).first() {
+        selected.0 = crop.id.clone();
+    }
+}
+
+fn designate_grow_zones(
+    mut commands: Commands,
+    mut designated: EventReader<DesignateZone>,
+    mut cleared: EventReader<ClearZone>,
+    selected: Res<SelectedCrop>,
+    zones: Query<(Entity, &GrowZone)>,
+) {
+    for event in designated.read() {
+        if event.tool != Tool::GrowZone || selected.0.is_empty() {
+            continue;
+        }
+        commands.spawn((
+            zone_sprite(event.rect, Color::rgba(0.2, 0.8, 0.2, 0.25)),
+            GrowZone {
+                crop: selected.0.clone(),
+                rect: event.rect,
+                tilled: HashSet::new(),
+            },
+        ));
+    }
+
+    for event in cleared.read() {
+        if event.tool != Tool::GrowZone {
+            continue;
+        }
+        for (entity, zone) in zones.iter() {
+            if zone.rect.contains(event.pos) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+pub fn find_farm_work(
+    zones: Query<(Entity, &GrowZone)>,
+    plants: Query<(&Plant, Has<Tended>)>,
+    plant_tiles: Res<PlantTiles>,
+    reservations: Res<Reservations>,
+    terrain: Res<TerrainMap>,
+    registry: Res<PlantRegistry>,
+    mut farm_work: ResMut<FarmWork>,
+    mut available_work: ResMut<AvailableWork>,
+) {
+    farm_work.targets.clear();
+    for (entity, zone) in zones.iter() {
+        let crop = registry.species.get(&zone.crop);
+        for pos in zone.rect.tiles() {
+            // tiles the crop would never take on are left alone
+            let fertile = terrain.tiles.get(&pos).is_some_and(|ground| crop.is_some_and(|crop| crop.can_grow(*ground)));
+            if !reservations.is_free(Target::Tile(pos)) || !fertile {
+                continue;
+            }
+            let plant = plant_tiles.plants.get(&pos).and_then(|entity| plants.get(*entity).ok());
+            let task = match plant {
+                // weeds and dead crops get cleared out of the way
+                Some((plant, _)) if plant.species != zone.crop || plant.stage == GrowthStage::Withered => AllTasks::Harvest,
+                Some((plant, _)) if plant.stage.harvestable() => AllTasks::Harvest,
+                Some((_, false)) => AllTasks::Tend,
+                Some((_, true)) => continue,
+                None if zone.tilled.contains(&pos) => AllTasks::Sow,
+                None => AllTasks::Till,
+            };
+            farm_work.targets.entry(task).or_default().push((pos, entity));
+        }
+    }
+
+    let tasks: Vec<AllTasks> = FARM_TASKS.into_iter().filter(|t| farm_work.targets.contains_key(t)).collect();
+    available_work.jobs.insert(WorkType::Growing, tasks);
+}
+
+// farmers pick the closest tile that needs the work they chose
+fn take_farm_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    farm_work: Res<FarmWork>,
+    query: Query<(Entity, &AllTasks, &Transform), (With<Character>, With<Busy>, Without<FarmJob>)>,
+    mut reservations: ResMut<Reservations>,
+) {
+    for (entity, task, transform) in query.iter() {
+        if !FARM_TASKS.contains(task) {
+            continue;
+        }
+        let here = Pos::from_translation(transform.translation);
+        let target = farm_work
+            .targets
+            .get(task)
+            .and_then(|targets| {
+                targets
+                    .iter()
+                    .filter(|(pos, _)| reservations.is_free(Target::Tile(*pos)))
+                    .min_by_key(|(pos, _)| pos.distance(&here))
+            });
+
+        match target {
+            Some((pos, zone)) => {
+                reservations.reserve(Target::Tile(*pos), entity, *task, time.elapsed_seconds());
+                commands.entity(entity).insert((
+                    FarmJob {
+                        task: *task,
+                        pos: *pos,
+                        zone: *zone,
+                        progress: 0.0,
+                    },
+                    NeedsPath { pos: *pos },
+                ));
+            }
+            // someone else got there first
+            None => {
+                commands.entity(entity).remove::<Busy>();
+            }
+        }
+    }
+}
+
+fn work_farm_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<
+        (Entity, &Transform, &mut FarmJob, Option<&PathFailed>, &mut Skills, &Mood, &Traits, Option<&Name>),
+        (Without<Path>, Without<NeedsPath>, Without<PathPending>),
+    >,
+    trait_registry: Res<TraitRegistry>,
+    mut reservations: ResMut<Reservations>,
+    mut done: EventWriter<FarmJobDone>,
+) {
+    for (entity, transform, mut job, path_failed, mut skills, mood, traits, name) in query.iter_mut() {
+        if path_failed.is_some() {
+            reservations.mark_unreachable(Target::Tile(job.pos), time.elapsed_seconds());
+            commands.entity(entity).remove::<(FarmJob, PathFailed, Busy)>();
+            continue;
+        }
+
+        let target = Vec2::new((job.pos.0 * SPRITE_SIZE) as f32, (job.pos.1 * SPRITE_SIZE) as f32);
+        if transform.translation.truncate().distance(target) > WORK_DISTANCE {
+            commands.entity(entity).insert(NeedsPath { pos: job.pos });
+            continue;
+        }
+
+        let speed = skills.work_speed(SkillType::Farming) * mood.work_speed() * trait_registry.product(traits, |d| d.work_speed);
+        job.progress += speed * time.delta_seconds();
+        if job.progress < work_amount(job.task) {
+            continue;
+        }
+
+        done.send(FarmJobDone {
+            task: job.task,
+            pos: job.pos,
+            zone: job.zone,
+            quality: skills.quality(SkillType::Farming, &mut thread_rng()),
+        });
+        commands.entity(entity).remove::<(FarmJob, Busy)>();
+
+        if skills.learn(SkillType::Farming, FARM_XP) {
+            if let Some(name) = name {
+                screen_print!(push, sec: 3.0, "{} got better at Farming", &name.0);
+            }
+        }
+    }
+}
+
+fn finish_farm_jobs(
+    mut commands: Commands,
+    mut done: EventReader<FarmJobDone>,
+    mut zones: Query<&mut GrowZone>,
+    plants: Query<(Entity, &Plant)>,
+    plant_tiles: Res<PlantTiles>,
+    registry: Res<PlantRegistry>,
+    mut place: EventWriter<PlacePlant>,
+    mut items: EventWriter<SpawnItem>,
+) {
+    for event in done.read() {
+        // the zone was removed while they were working on it
+        let Ok(mut zone) = zones.get_mut(event.zone) else {
+            continue;
+        };
+        let plant = plant_tiles.plants.get(&event.pos).and_then(|entity| plants.get(*entity).ok());
+
+        match event.task {
+            AllTasks::Till => {
+                if !zone.tilled.insert(event.pos) {
+                    continue;
+                }
+                let offset = Vec2::new((event.pos.0 * SPRITE_SIZE) as f32, (event.pos.1 * SPRITE_SIZE) as f32) - zone.rect.center();
+                commands.entity(event.zone).with_children(|parent| {
+                    parent.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::rgba(0.35, 0.25, 0.15, 0.6),
+                            custom_size: Some(Vec2::splat(SPRITE_SIZE as f32)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(offset.extend(1.0)),
+                        ..default()
+                    });
+                });
+            }
+            AllTasks::Sow => {
+                place.send(PlacePlant {
+                    species: zone.crop.clone(),
+                    pos: event.pos,
+                });
+            }
+            AllTasks::Tend => {
+                if let Some((entity, _)) = plant {
+                    commands.entity(entity).insert(Tended { remaining: TENDED_DURATION });
+                }
+            }
+            AllTasks::Harvest => {
+                let Some((entity, plant)) = plant else {
+                    continue;
+                };
+                commands.entity(entity).despawn_recursive();
+
+                if !plant.stage.harvestable() {
+                    continue;
+                }
+                let Some(harvest) = registry.species.get(&plant.species).and_then(|s| s.harvest.as_ref()) else {
+                    continue;
+                };
+                // a skilled farmer gets up to half again as much out of a plant, a novice half as much
+                let count = (harvest.amount as f32 * (0.5 + event.quality)).round().max(1.0) as u32;
+                items.send(SpawnItem {
+                    def: harvest.item.clone(),
+                    count,
+                    pos: event.pos,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+// the farmer went off to do something more important
+fn abandon_farm_jobs(mut commands: Commands, query: Query<(Entity, &FarmJob, &AllTasks)>) {
+    for (entity, job, task) in query.iter() {
+        if job.task != *task {
+            commands.entity(entity).remove::<FarmJob>();
+        }
+    }
+}
+
+fn crop_picker(ui: &mut egui::Ui, id: impl std::hash::Hash, crops: &[&PlantSpecies], crop: &mut String) {
+    let selected = crops.iter().find(|c| c.id == *crop).map(|c| c.name.as_str()).unwrap_or("");
+    egui::ComboBox::from_id_source(id).selected_text(selected).show_ui(ui, |ui| {
+        for species in crops {
+            ui.selectable_value(crop, species.id.clone(), &species.name);
+        }
+    });
+}
+
+fn farming_ui(
+    mut contexts: EguiContexts,
+    registry: Res<PlantRegistry>,
+    mut selected: ResMut<SelectedCrop>,
+    mut zones: Query<(Entity, &mut GrowZone)>,
+) {
+    let crops = farmable_crops(&registry);
+
+    egui::Window::new("Farming")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("New zones grow");
+                crop_picker(ui, "new_zone_crop", &crops, &mut selected.0);
+            });
+            ui.separator();
+
+            for (entity, mut zone) in zones.iter_mut() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} tiles, {} tilled", zone.rect.area(), zone.tilled.len()));
+                    // only touch the zone when the crop actually changes, so it isn't marked changed every frame
+                    let mut crop = zone.crop.clone();
+                    crop_picker(ui, entity, &crops, &mut crop);
+                    if crop != zone.crop {
+                        zone.crop = crop;
+                    }
+                });
+            }
+        });
+}
+
+pub struct FarmingPlugin;
+
+impl Plugin for FarmingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedCrop>()
+            .init_resource::<FarmWork>()
+            .add_event::<FarmJobDone>()
+            .add_systems(OnEnter(InGame), pick_default_crop)
+            .add_systems(Update, designate_grow_zones.run_if(in_state(InGame)))
+            .add_systems(Update, find_farm_work.after(designate_grow_zones).run_if(in_state(InGame)))
+            .add_systems(Update, take_farm_jobs.after(find_farm_work).run_if(in_state(InGame)))
+            .add_systems(Update, work_farm_jobs.after(take_farm_jobs).run_if(in_state(InGame)))
+            .add_systems(Update, finish_farm_jobs.after(work_farm_jobs).run_if(in_state(InGame)))
+            .add_systems(Update, renew_claims::<FarmJob>.run_if(in_state(InGame)))
+            .add_systems(Update, abandon_farm_jobs.run_if(in_state(InGame)))
+            .add_systems(Update, farming_ui.run_if(in_state(InGame)));
+    }
+}
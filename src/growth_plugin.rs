@@ -1,10 +1,234 @@
 use bevy::app::{App, Plugin};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_debug_text_overlay::screen_print;
+use rand::prelude::*;
+use serde::Deserialize;
+use strum_macros::AsRefStr;
+
+use crate::AppState::InGame;
+use crate::clock_plugin::GameClock;
+use crate::pathing::Pos;
+use crate::temperature_plugin::{Hardiness, Temperature, PLANT_EXPOSURE_LIMIT};
+use crate::weather_plugin::Weather;
+use crate::world_gen_plugin::{TerrainKind, TerrainMap, SPRITE_SIZE};
 //use bevy_inspector_egui::prelude::ReflectInspectorOptions;
 //use bevy_inspector_egui::InspectorOptions;
 
+const PLANT_DATA: &str = include_str!("../assets/data/plants.json");
+
+// above the tilemap, under the night tint
+const PLANT_Z: f32 = 40.0;
+
+// scattered around where the colonists start
+const STARTING_PLANTS: usize = 40;
+const STARTING_AREA: (i32, i32) = (31, 20);
+
+// plants stop spreading once there are this many
+const MAX_PLANTS: usize = 2000;
+// fertility is multiplied by this on terrain the species doesn't prefer
+const UNPREFERRED_TERRAIN: f32 = 0.4;
+// nothing takes root where the fertility is lower than this, e.g. grass-loving species in mud
+const MIN_FERTILITY: f32 = 0.3;
+
+// tended plants grow this much faster, for this many seconds
+const TENDED_GROWTH: f32 = 1.5;
+pub const TENDED_DURATION: f32 = 60.0;
+
+fn one() -> f32 {
+    1.0
+}
+
+fn white() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, AsRefStr)]
+pub enum GrowthStage {
+    Seed,
+    Sprout,
+    Mature,
+    Flowering,
+    Withered,
+}
+
+impl GrowthStage {
+    pub fn harvestable(&self) -> bool {
+        matches!(self, GrowthStage::Mature | GrowthStage::Flowering)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StageDef {
+    pub stage: GrowthStage,
+    // the plant reaches this stage once its age gets here
+    pub age: f32,
+    pub sprite: String,
+    #[serde(default = "one")]
+    pub scale: f32,
+    #[serde(default = "white")]
+    pub tint: [f32; 3],
+}
+
+// what a plant gives when it's harvested while mature
+#[derive(Deserialize, Debug, Clone)]
+pub struct HarvestDef {
+    pub item: String,
+    pub amount: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PlantSpecies {
+    pub id: String,
+    pub name: String,
+    pub grow_rate: f32,
+    pub min_temperature: f32,
+    pub max_temperature: f32,
+    pub preferred_terrain: TerrainKind,
+    // in tiles, how far seeds land from the parent plant
+    pub spread_radius: i32,
+    // chance per second of dropping a seed while flowering
+    pub seed_chance: f64,
+    // the plant dies once it gets this old
+    pub lifespan: f32,
+    // wild-only plants have nothing to harvest and can't be farmed
+    pub harvest: Option<HarvestDef>,
+    // ordered by age
+    pub stages: Vec<StageDef>,
+}
+
+impl PlantSpecies {
+    pub fn stage_at(&self, age: f32) -> &StageDef {
+        self.stages.iter().rev().find(|s| age >= s.age).unwrap_or(&self.stages[0])
+    }
+
+    // 0.0 - 1.0, the chance of a seed taking root on this terrain
+    pub fn fertility(&self, terrain: TerrainKind) -> f32 {
+        if terrain == self.preferred_terrain {
+            terrain.fertility()
+        } else {
+            terrain.fertility() * UNPREFERRED_TERRAIN
+        }
+    }
+
+    pub fn can_grow(&self, terrain: TerrainKind) -> bool {
+        self.fertility(terrain) >= MIN_FERTILITY
+    }
+}
+
+#[derive(Resource)]
+pub struct PlantRegistry {
+    pub species: HashMap<String, PlantSpecies>,
+    // keyed by sprite path
+    pub textures: HashMap<String, Handle<Image>>,
+}
+
+impl PlantRegistry {
+    // everything needed for a freshly sown plant, or None for an unknown species
+    pub fn bundle(&self, species: &str, pos: Pos) -> Option<PlantBundle> {
+        let def = self.species.get(species)?;
+        let stage = def.stage_at(0.0);
+
+        Some(PlantBundle {
+            plant: Plant {
+                species: def.id.clone(),
+                stage: stage.stage,
+            },
+            growth: Growth {
+                age: 0.0,
+                grow_rate: def.grow_rate,
+            },
+            hardiness: Hardiness::new(def.min_temperature, def.max_temperature),
+            pos,
+            sprite: SpriteBundle {
+                texture: self.textures[&stage.sprite].clone(),
+                sprite: Sprite {
+                    color: Color::rgb(stage.tint[0], stage.tint[1], stage.tint[2]),
+                    ..default()
+                },
+                transform: Transform::from_xyz(
+                    (pos.0 * SPRITE_SIZE) as f32,
+                    (pos.1 * SPRITE_SIZE) as f32,
+                    PLANT_Z,
+                )
+                .with_scale(Vec3::splat(stage.scale)),
+                ..default()
+            },
+        })
+    }
+}
+
+// which plant stands on which tile. At most one plant per tile
+#[derive(Resource, Default)]
+pub struct PlantTiles {
+    pub plants: HashMap<Pos, Entity>,
+}
+
+// puts a new plant of `species` on a tile, if the tile is free and anything can grow there
+#[derive(Event)]
+pub struct PlacePlant {
+    pub species: String,
+    pub pos: Pos,
+}
+
+#[derive(Debug)]
+pub enum PlaceError {
+    UnknownSpecies,
+    Occupied,
+    Infertile,
+}
+
+// every plant is spawned through here, so the tile index always knows about it
+fn place_plant(
+    commands: &mut Commands,
+    registry: &PlantRegistry,
+    terrain: &TerrainMap,
+    tiles: &mut PlantTiles,
+    species: &str,
+    pos: Pos,
+    age: f32,
+) -> Result<Entity, PlaceError> {
+    let def = registry.species.get(species).ok_or(PlaceError::UnknownSpecies)?;
+    if tiles.plants.contains_key(&pos) || terrain.built(pos).next().is_some() {
+        return Err(PlaceError::Occupied);
+    }
+    // off the map counts as infertile
+    let ground = terrain.tiles.get(&pos).ok_or(PlaceError::Infertile)?;
+    if !def.can_grow(*ground) {
+        return Err(PlaceError::Infertile);
+    }
+
+    let mut bundle = registry.bundle(species, pos).ok_or(PlaceError::UnknownSpecies)?;
+    bundle.growth.age = age;
+    let entity = commands.spawn(bundle).id();
+    tiles.plants.insert(pos, entity);
+    Ok(entity)
+}
+
 #[derive(Component)]
-pub struct Plant;
+pub struct Plant {
+    pub species: String,
+    pub stage: GrowthStage,
+}
+
+// ready to be harvested
+#[derive(Component)]
+pub struct Mature;
+
+// recently weeded and watered by a farmer
+#[derive(Component)]
+pub struct Tended {
+    pub remaining: f32,
+}
+
+#[derive(Bundle)]
+pub struct PlantBundle {
+    plant: Plant,
+    growth: Growth,
+    hardiness: Hardiness,
+    pos: Pos,
+    sprite: SpriteBundle,
+}
 
 #[derive(Reflect, Resource, Default, /*InspectorOptions,*/ Component)]
 #[reflect(Resource/*, InspectorOptions*/)]
@@ -15,14 +239,212 @@ pub struct Growth {
 
 pub struct PlanGrowthPlugin;
 
-fn grow_tick(time: Res<Time>, mut query: Query<&mut Growth, With<Plant>>) {
-    for mut growable in query.iter_mut() {
-        growable.age += growable.grow_rate * time.delta_seconds();
+fn grow_tick(
+    time: Res<Time>,
+    clock: Res<GameClock>,
+    weather: Res<Weather>,
+    temperature: Res<Temperature>,
+    mut query: Query<(&mut Growth, &Transform, Option<&Hardiness>, Option<&Tended>), With<Plant>>,
+) {
+    let multiplier = weather.growth_multiplier(clock.season);
+    for (mut growable, transform, hardiness, tended) in query.iter_mut() {
+        // plants don't grow while it's too cold or hot for them
+        let here = temperature.at(Pos::from_translation(transform.translation));
+        if hardiness.is_some_and(|h| !h.survives(here)) {
+            continue;
+        }
+        let care = if tended.is_some() { TENDED_GROWTH } else { 1.0 };
+        growable.age += growable.grow_rate * multiplier * care * time.delta_seconds();
+    }
+}
+
+fn wear_off_tending(mut commands: Commands, time: Res<Time>, mut query: Query<(Entity, &mut Tended)>) {
+    for (entity, mut tended) in query.iter_mut() {
+        tended.remaining -= time.delta_seconds();
+        if tended.remaining <= 0.0 {
+            commands.entity(entity).remove::<Tended>();
+        }
+    }
+}
+
+fn load_plant_registry(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let defs: Vec<PlantSpecies> = serde_json::from_str(PLANT_DATA).expect("invalid plant data");
+
+    let mut textures = HashMap::new();
+    for stage in defs.iter().flat_map(|d| d.stages.iter()) {
+        textures
+            .entry(stage.sprite.clone())
+            .or_insert_with(|| asset_server.load(stage.sprite.clone()));
+    }
+
+    commands.insert_resource(PlantRegistry {
+        species: defs.into_iter().map(|d| (d.id.clone(), d)).collect(),
+        textures,
+    });
+}
+
+fn spawn_starting_plants(
+    mut commands: Commands,
+    registry: Res<PlantRegistry>,
+    terrain: Res<TerrainMap>,
+    mut tiles: ResMut<PlantTiles>,
+) {
+    let mut rng = thread_rng();
+    let species: Vec<&String> = registry.species.keys().collect();
+
+    for _ in 0..STARTING_PLANTS {
+        let pos = Pos(
+            rng.gen_range(-STARTING_AREA.0..=STARTING_AREA.0),
+            rng.gen_range(-STARTING_AREA.1..=STARTING_AREA.1),
+        );
+        // not everything starts out as a seed
+        let age = rng.gen_range(0.0..150.0);
+        let _ = place_plant(&mut commands, &registry, &terrain, &mut tiles, species.choose(&mut rng).unwrap(), pos, age);
+    }
+}
+
+fn place_plants(
+    mut commands: Commands,
+    mut events: EventReader<PlacePlant>,
+    registry: Res<PlantRegistry>,
+    terrain: Res<TerrainMap>,
+    mut tiles: ResMut<PlantTiles>,
+) {
+    for event in events.read() {
+        if let Err(error) = place_plant(&mut commands, &registry, &terrain, &mut tiles, &event.species, event.pos, 0.0) {
+            screen_print!(push, sec: 3.0, "Can't plant {} at {:?}: {:?}", event.species, event.pos, error);
+        }
+    }
+}
+
+// keeps the tile index in step with plants dying, being harvested etc.
+fn forget_removed_plants(mut removed: RemovedComponents<Plant>, mut tiles: ResMut<PlantTiles>) {
+    let removed: Vec<Entity> = removed.read().collect();
+    if removed.is_empty() {
+        return;
+    }
+    tiles.plants.retain(|_, entity| !removed.contains(entity));
+}
+
+// swaps the sprite when a plant grows into its next stage
+fn update_stage(
+    mut commands: Commands,
+    registry: Res<PlantRegistry>,
+    mut query: Query<(Entity, &mut Plant, &Growth, &mut Handle<Image>, &mut Sprite, &mut Transform), Changed<Growth>>,
+) {
+    for (entity, mut plant, growth, mut texture, mut sprite, mut transform) in query.iter_mut() {
+        let Some(species) = registry.species.get(&plant.species) else {
+            continue;
+        };
+        let stage = species.stage_at(growth.age);
+        if stage.stage == plant.stage {
+            continue;
+        }
+
+        plant.stage = stage.stage;
+        *texture = registry.textures[&stage.sprite].clone();
+        sprite.color = Color::rgb(stage.tint[0], stage.tint[1], stage.tint[2]);
+        transform.scale = Vec3::splat(stage.scale);
+
+        if stage.stage.harvestable() {
+            commands.entity(entity).insert(Mature);
+        } else {
+            commands.entity(entity).remove::<Mature>();
+        }
+    }
+}
+
+// flowering plants drop seeds around them, which take root depending on the ground
+fn spread_plants(
+    mut commands: Commands,
+    time: Res<Time>,
+    registry: Res<PlantRegistry>,
+    terrain: Res<TerrainMap>,
+    mut tiles: ResMut<PlantTiles>,
+    query: Query<(&Plant, &Pos)>,
+) {
+    let count = tiles.plants.len();
+    if count >= MAX_PLANTS {
+        return;
+    }
+    let mut room = MAX_PLANTS - count;
+
+    let mut rng = thread_rng();
+
+    for (plant, pos) in query.iter() {
+        if plant.stage != GrowthStage::Flowering {
+            continue;
+        }
+        let Some(species) = registry.species.get(&plant.species) else {
+            continue;
+        };
+        if !rng.gen_bool((species.seed_chance * time.delta_seconds() as f64).min(1.0)) {
+            continue;
+        }
+
+        let radius = species.spread_radius;
+        let target = Pos(
+            pos.0 + rng.gen_range(-radius..=radius),
+            pos.1 + rng.gen_range(-radius..=radius),
+        );
+        // seeds only take root some of the time, depending on the ground
+        let fertility = terrain.tiles.get(&target).map(|ground| species.fertility(*ground)).unwrap_or(0.0);
+        if !rng.gen_bool(fertility.clamp(0.0, 1.0) as f64) {
+            continue;
+        }
+        if place_plant(&mut commands, &registry, &terrain, &mut tiles, &species.id, target, 0.0).is_err() {
+            continue;
+        }
+
+        room -= 1;
+        if room == 0 {
+            break;
+        }
+    }
+}
+
+fn die_of_old_age(mut commands: Commands, registry: Res<PlantRegistry>, query: Query<(Entity, &Plant, &Growth)>) {
+    for (entity, plant, growth) in query.iter() {
+        if registry.species.get(&plant.species).is_some_and(|s| growth.age >= s.lifespan) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// plants left outside their temperature range for too long die
+fn plant_exposure(
+    mut commands: Commands,
+    time: Res<Time>,
+    temperature: Res<Temperature>,
+    mut query: Query<(Entity, &Transform, &mut Hardiness), With<Plant>>,
+) {
+    for (entity, transform, mut hardiness) in query.iter_mut() {
+        let here = temperature.at(Pos::from_translation(transform.translation));
+        if hardiness.survives(here) {
+            hardiness.exposure = (hardiness.exposure - time.delta_seconds()).max(0.0);
+            continue;
+        }
+
+        hardiness.exposure += time.delta_seconds();
+        if hardiness.exposure >= PLANT_EXPOSURE_LIMIT {
+            commands.entity(entity).despawn_recursive();
+        }
     }
 }
 
 impl Plugin for PlanGrowthPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, grow_tick);
+        app.init_resource::<PlantTiles>()
+            .add_event::<PlacePlant>()
+            .add_systems(Startup, load_plant_registry)
+            .add_systems(OnEnter(InGame), spawn_starting_plants)
+            .add_systems(Update, grow_tick)
+            .add_systems(Update, wear_off_tending)
+            .add_systems(Update, update_stage.after(grow_tick))
+            .add_systems(Update, place_plants.run_if(in_state(InGame)))
+            .add_systems(Update, spread_plants.run_if(in_state(InGame)))
+            .add_systems(Update, forget_removed_plants)
+            .add_systems(Update, die_of_old_age)
+            .add_systems(Update, plant_exposure);
     }
 }
@@ -0,0 +1,178 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy_debug_text_overlay::screen_print;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::name_plugin::Name;
+use crate::task_scorer::Busy;
+use crate::tasks::*;
+use crate::wander_plugin::stop_moving;
+
+// health lost per second while a need is empty
+const STARVATION_DAMAGE: f32 = 1.0;
+const DEHYDRATION_DAMAGE: f32 = 2.0;
+// health regained per second while no need is empty
+const HEAL_RATE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageCause {
+    Starvation,
+    Dehydration,
+    Hypothermia,
+    Heatstroke,
+}
+
+#[derive(Component)]
+pub struct Health {
+    pub value: f32,
+    pub max: f32,
+    pub last_damage: Option<DamageCause>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            value: 100.0,
+            max: 100.0,
+            last_damage: None,
+        }
+    }
+}
+
+impl Health {
+    pub fn damage(&mut self, amount: f32, cause: DamageCause) {
+        self.value -= amount;
+        self.last_damage = Some(cause);
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        self.value = (self.value + amount).min(self.max);
+    }
+}
+
+// Passed out from exhaustion. The colonist sleeps where it fell until rested
+#[derive(Component)]
+pub struct Collapsed;
+
+#[allow(unused)]
+#[derive(Component)]
+pub struct Corpse {
+    pub name: String,
+    pub cause: Option<DamageCause>,
+}
+
+#[allow(unused)]
+#[derive(Event)]
+pub struct ColonistDied {
+    pub entity: Entity,
+    pub corpse: Entity,
+    pub name: String,
+    pub cause: Option<DamageCause>,
+    pub translation: Vec3,
+}
+
+fn need_damage(time: Res<Time>, mut query: Query<(&mut Health, &Hunger, &Thirst), With<Character>>) {
+    for (mut health, hunger, thirst) in query.iter_mut() {
+        let mut starving = false;
+
+        if hunger.value <= 0.0 {
+            health.damage(STARVATION_DAMAGE * time.delta_seconds(), DamageCause::Starvation);
+            starving = true;
+        }
+        if thirst.value <= 0.0 {
+            health.damage(DEHYDRATION_DAMAGE * time.delta_seconds(), DamageCause::Dehydration);
+            starving = true;
+        }
+
+        if !starving {
+            health.heal(HEAL_RATE * time.delta_seconds());
+        }
+    }
+}
+
+fn collapse_from_exhaustion(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Sleep, &mut AllTasks, Option<&Name>), (With<Character>, Without<Collapsed>)>,
+) {
+    for (entity, sleep, mut task, name) in query.iter_mut() {
+        if sleep.value > 0.0 {
+            continue;
+        }
+
+        if let Some(name) = name {
+            screen_print!(push, sec: 5.0, "{} collapsed from exhaustion", &name.0);
+        }
+
+        // Busy keeps the scorer from picking something else until the sleep task finishes
+        *task = AllTasks::Sleep;
+        stop_moving(commands.entity(entity).insert((Busy, Collapsed)));
+    }
+}
+
+fn recover_from_collapse(mut commands: Commands, query: Query<Entity, (With<Collapsed>, Without<Busy>)>) {
+    for entity in query.iter() {
+        commands.entity(entity).remove::<Collapsed>();
+    }
+}
+
+fn die(
+    mut commands: Commands,
+    mut died: EventWriter<ColonistDied>,
+    query: Query<(Entity, &Health, Option<&Name>, &Transform, &Handle<Image>, &TextureAtlas), With<Character>>,
+) {
+    for (entity, health, name, transform, texture, atlas) in query.iter() {
+        if health.value > 0.0 {
+            continue;
+        }
+
+        let name = name.map(|n| n.0.clone()).unwrap_or("Someone".to_string());
+        screen_print!(push, sec: 10.0, "{} died ({:?})", &name, health.last_damage);
+
+        let mut corpse_transform = *transform;
+        corpse_transform.rotate_z(std::f32::consts::FRAC_PI_2);
+        corpse_transform.translation.z -= 1.0;
+
+        let corpse = commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::GRAY,
+                        ..default()
+                    },
+                    texture: texture.clone(),
+                    transform: corpse_transform,
+                    ..default()
+                },
+                atlas.clone(),
+                Corpse {
+                    name: name.clone(),
+                    cause: health.last_damage,
+                },
+            ))
+            .id();
+
+        died.send(ColonistDied {
+            entity,
+            corpse,
+            name,
+            cause: health.last_damage,
+            translation: transform.translation,
+        });
+
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ColonistDied>()
+            .add_systems(Update, need_damage.run_if(in_state(InGame)))
+            .add_systems(Update, collapse_from_exhaustion.run_if(in_state(InGame)))
+            .add_systems(Update, recover_from_collapse.run_if(in_state(InGame)))
+            // after everything else has had its commands applied, so nothing touches the despawned colonist
+            .add_systems(Last, die.run_if(in_state(InGame)));
+    }
+}
@@ -1,19 +1,109 @@
 use bevy::app::{App, Plugin, Startup};
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts};
 use leafwing_input_manager::{Actionlike, InputManagerBundle};
 use leafwing_input_manager::input_map::InputMap;
 use leafwing_input_manager::plugin::InputManagerPlugin;
 use leafwing_input_manager::prelude::ActionState;
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter};
 
 use crate::AppState::InGame;
+use crate::growth_plugin::PlacePlant;
+use crate::pathing::Pos;
 use crate::world_gen_plugin::SPRITE_SIZE;
 
 pub struct InputPlugin;
 
+// what a plain left click on the world plants
+const CLICK_PLANT: &str = "wildflower";
+
 #[derive(Resource, Default)]
 struct MyWorldCoords(Vec2);
 
+// zone overlays sit above the tilemap, under the plants
+const ZONE_Z: f32 = 30.0;
+
+// what clicking on the world does
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq, EnumIter, AsRefStr)]
+pub enum Tool {
+    #[default]
+    Select,
+    GrowZone,
+    Stockpile,
+    Build,
+}
+
+// a rectangle of tiles. Both corners are inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileRect {
+    pub min: Pos,
+    pub max: Pos,
+}
+
+impl TileRect {
+    pub fn new(a: Pos, b: Pos) -> Self {
+        TileRect {
+            min: Pos(a.0.min(b.0), a.1.min(b.1)),
+            max: Pos(a.0.max(b.0), a.1.max(b.1)),
+        }
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        (self.min.0..=self.max.0).contains(&pos.0) && (self.min.1..=self.max.1).contains(&pos.1)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = Pos> + '_ {
+        (self.min.0..=self.max.0).flat_map(move |x| (self.min.1..=self.max.1).map(move |y| Pos(x, y)))
+    }
+
+    pub fn area(&self) -> i32 {
+        (self.max.0 - self.min.0 + 1) * (self.max.1 - self.min.1 + 1)
+    }
+
+    // in world units
+    pub fn center(&self) -> Vec2 {
+        Vec2::new((self.min.0 + self.max.0) as f32, (self.min.1 + self.max.1) as f32) * SPRITE_SIZE as f32 / 2.0
+    }
+
+    // in world units
+    pub fn size(&self) -> Vec2 {
+        Vec2::new((self.max.0 - self.min.0 + 1) as f32, (self.max.1 - self.min.1 + 1) as f32) * SPRITE_SIZE as f32
+    }
+}
+
+// the translucent overlay that shows where a zone is
+pub fn zone_sprite(rect: TileRect, color: Color) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(rect.size()),
+            ..default()
+        },
+        transform: Transform::from_translation(rect.center().extend(ZONE_Z)),
+        ..default()
+    }
+}
+
+// the corner the player started dragging a zone from
+#[derive(Resource, Default)]
+struct ZoneDrag(Option<Pos>);
+
+// the player dragged out a rectangle of tiles with a zone tool
+#[derive(Event)]
+pub struct DesignateZone {
+    pub tool: Tool,
+    pub rect: TileRect,
+}
+
+// the player right clicked a tile with a zone tool
+#[derive(Event)]
+pub struct ClearZone {
+    pub tool: Tool,
+    pub pos: Pos,
+}
+
 #[derive(Actionlike, PartialEq, Eq, Hash, Clone, Copy, Debug, Reflect)]
 enum Action {
     Spawn,
@@ -27,8 +117,14 @@ impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(InputManagerPlugin::<Action>::default())
             .init_resource::<MyWorldCoords>()
+            .init_resource::<Tool>()
+            .init_resource::<ZoneDrag>()
+            .add_event::<DesignateZone>()
+            .add_event::<ClearZone>()
             .add_systems(Startup, setup)
             .add_systems(Update, my_cursor_system)
+            .add_systems(Update, tools_ui.run_if(in_state(InGame)))
+            .add_systems(Update, designate_zones.after(my_cursor_system).run_if(in_state(InGame)))
             .add_systems(Update, jump.run_if(in_state(InGame)));
     }
 }
@@ -63,51 +159,83 @@ fn setup(mut commands: Commands) {
         .insert(GlobalInput);
 }
 
+fn tools_ui(mut contexts: EguiContexts, mut tool: ResMut<Tool>, mut drag: ResMut<ZoneDrag>) {
+    egui::Window::new("Tools").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            for option in Tool::iter() {
+                if ui.selectable_label(*tool == option, option.as_ref()).clicked() {
+                    *tool = option;
+                    drag.0 = None;
+                }
+            }
+        });
+    });
+}
+
+// drag with the left button to mark out a zone, right click to clear the zone under the cursor
+fn designate_zones(
+    query: Query<&ActionState<Action>, With<GlobalInput>>,
+    tool: Res<Tool>,
+    cursor_pos: Res<MyWorldCoords>,
+    mut drag: ResMut<ZoneDrag>,
+    mut designated: EventWriter<DesignateZone>,
+    mut cleared: EventWriter<ClearZone>,
+    mut contexts: EguiContexts,
+    mut gizmos: Gizmos,
+) {
+    if *tool == Tool::Select {
+        return;
+    }
+    let action_state = query.single();
+    let here = Pos::from_translation(cursor_pos.0.extend(0.0));
+
+    if let Some(start) = drag.0 {
+        let rect = TileRect::new(start, here);
+        if action_state.just_released(&Action::Spawn) {
+            designated.send(DesignateZone { tool: *tool, rect });
+            drag.0 = None;
+        } else {
+            gizmos.rect_2d(rect.center(), 0.0, rect.size(), Color::YELLOW);
+        }
+        return;
+    }
+
+    // clicks on the UI windows shouldn't reach the world
+    if contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+
+    if action_state.just_pressed(&Action::Spawn) {
+        drag.0 = Some(here);
+    }
+    if action_state.just_pressed(&Action::Despawn) {
+        cleared.send(ClearZone { tool: *tool, pos: here });
+    }
+}
+
 // Query for the `ActionState` component in your game logic systems!
 fn jump(query: Query<&ActionState<Action>, With<GlobalInput>>,
-        asset_server: Res<AssetServer>,
         cursor_pos: Res<MyWorldCoords>,
-        mut commands: Commands,
+        tool: Res<Tool>,
+        mut place: EventWriter<PlacePlant>,
+        mut contexts: EguiContexts,
 ) {
+    // the zone tools use the mouse buttons for themselves
+    if *tool != Tool::Select {
+        return;
+    }
     let action_state = query.single();
 
+    // clicks on the UI windows shouldn't reach the world
+    if contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+
     // Each action has a button-like state of its own that you can check
     if action_state.just_pressed(&Action::Spawn) {
-        let ugly_flower: Handle<Image> = asset_server.get_handle("plants/ugly_flower.png").unwrap();
-
-        let sprite_size = SPRITE_SIZE as f32;
-
-        let x_offset = match cursor_pos.0.x {
-            x if x < 0.0 => { sprite_size / -2.0 }
-            x if x >= 0.0 => { sprite_size / 2.0 }
-            _ => { 0.0 }
-        };
-
-        let y_offset = match cursor_pos.0.y {
-            y if y < 0.0 => { sprite_size / -2.0 }
-            y if y >= 0.0 => { sprite_size / 2.0 }
-            _ => { 0.0 }
-        };
-
-        let cursor_x = (cursor_pos.0.x as i32 / SPRITE_SIZE) as f32 * sprite_size + x_offset;
-        let cursor_y = (cursor_pos.0.y as i32 / SPRITE_SIZE) as f32 * sprite_size + y_offset;
-
-        commands.spawn(SpriteBundle {
-            sprite: Default::default(),
-            transform: Transform {
-                translation: Vec3::new(
-                    cursor_x,
-                    cursor_y,
-                    500.0,
-                ),
-                rotation: Default::default(),
-                scale: Vec3::splat(1.0),
-            },
-            global_transform: Default::default(),
-            texture: ugly_flower,
-            visibility: Visibility::Visible,
-            inherited_visibility: Default::default(),
-            view_visibility: Default::default(),
+        place.send(PlacePlant {
+            species: CLICK_PLANT.to_string(),
+            pos: Pos::from_translation(cursor_pos.0.extend(0.0)),
         });
     }
     if action_state.just_pressed(&Action::Despawn) {}
@@ -0,0 +1,537 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::utils::HashMap;
+use bevy_debug_text_overlay::screen_print;
+use serde::Deserialize;
+use strum_macros::{AsRefStr, EnumIter};
+
+use crate::AppState::InGame;
+use crate::pathing::Pos;
+use crate::world_gen_plugin::{edit_tiles, EditTile, StructureKind, TerrainMap, SPRITE_SIZE};
+
+// every content pack of item definitions. Later packs can't reuse ids from earlier ones
+const ITEM_PACKS: [&str; 3] = [
+    include_str!("../assets/data/items/food-ocal.json"),
+    include_str!("../assets/data/items/materials.json"),
+    include_str!("../assets/data/items/waste.json"),
+];
+
+// above plants, under the night tint
+const ITEM_Z: f32 = 45.0;
+const ITEM_SCALE: f32 = 0.7;
+// how far from the tile it was dropped on a new stack may end up
+const MAX_SPILL_RADIUS: i32 = 3;
+
+// carried items are drawn small, above the carrier's head
+const CARRIED_Z: f32 = 110.0;
+const CARRIED_SCALE: f32 = 0.4;
+const CARRIED_OFFSET: f32 = SPRITE_SIZE as f32 * 0.6;
+const CARRIED_SPACING: f32 = SPRITE_SIZE as f32 * 0.3;
+
+// kilograms a colonist can carry
+pub const CARRY_CAPACITY: f32 = 35.0;
+// colonists carrying more than this share of their capacity start slowing down
+const HEAVY_LOAD: f32 = 0.5;
+// how fast someone carrying as much as they can walks, relative to their normal speed
+const FULL_LOAD_SPEED: f32 = 0.6;
+
+// the supplies the colonists arrive with, a few days' worth of food and enough to put up a shelter
+const STARTING_ITEMS: [(&str, u32); 8] = [
+    ("bread_loaf", 20),
+    ("apple", 40),
+    ("carrot", 40),
+    ("potato", 30),
+    ("cheese_wedge", 10),
+    ("sandwich_01", 10),
+    ("wood", 150),
+    ("stone", 50),
+];
+const STARTING_ITEMS_POS: Pos = Pos(0, 0);
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, AsRefStr)]
+pub enum ItemCategory {
+    RawFood,
+    Meal,
+    Drink,
+    Material,
+    Waste,
+}
+
+impl ItemCategory {
+    pub fn is_food(&self) -> bool {
+        matches!(self, ItemCategory::RawFood | ItemCategory::Meal | ItemCategory::Drink)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ItemDef {
+    pub id: String,
+    pub name: String,
+    pub category: ItemCategory,
+    pub stack_size: u32,
+    // kilograms, per item
+    pub weight: f32,
+    // hunger restored by eating one. 0 for anything that isn't food
+    #[serde(default)]
+    pub nutrition: f32,
+    // game days until it rots. Never, if missing
+    pub spoil_days: Option<f32>,
+    // what one is worth, for the colony's wealth
+    #[serde(default)]
+    pub value: f32,
+    pub sprite: String,
+}
+
+#[derive(Resource)]
+pub struct ItemRegistry {
+    pub defs: HashMap<String, ItemDef>,
+    // keyed by sprite path
+    pub textures: HashMap<String, Handle<Image>>,
+}
+
+impl ItemRegistry {
+    pub fn get(&self, id: &str) -> Option<&ItemDef> {
+        self.defs.get(id)
+    }
+}
+
+// a stack of something lying on a tile
+#[derive(Component)]
+pub struct Item {
+    pub def: String,
+    pub count: u32,
+    // 0.0 - 1.0, how far the stack is through its shelf life
+    pub rot: f32,
+}
+
+impl Item {
+    // adds `count` more that are `rot` along, so the stack ends up as rotten as the mix
+    pub fn absorb(&mut self, count: u32, rot: f32) {
+        let total = self.count + count;
+        if total > 0 {
+            self.rot = (self.rot * self.count as f32 + rot * count as f32) / total as f32;
+        }
+        self.count = total;
+    }
+}
+
+#[derive(Component)]
+struct ItemCountText;
+
+// picked up and not on any tile. Follows whoever carries it around
+#[derive(Component)]
+pub struct Carried {
+    pub by: Entity,
+}
+
+// the stacks a colonist is carrying, and how much they weigh together
+#[derive(Component)]
+pub struct Inventory {
+    pub items: Vec<Entity>,
+    pub weight: f32,
+    pub capacity: f32,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory {
+            items: vec![],
+            weight: 0.0,
+            capacity: CARRY_CAPACITY,
+        }
+    }
+}
+
+impl Inventory {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn free_weight(&self) -> f32 {
+        (self.capacity - self.weight).max(0.0)
+    }
+
+    // full speed up to a heavy load, then slower the more is carried
+    pub fn speed_factor(&self) -> f32 {
+        let load = (self.weight / self.capacity).clamp(0.0, 1.0);
+        if load <= HEAVY_LOAD {
+            return 1.0;
+        }
+        1.0 - (1.0 - FULL_LOAD_SPEED) * (load - HEAVY_LOAD) / (1.0 - HEAVY_LOAD)
+    }
+
+    // puts everything down around `pos`
+    pub fn drop_all(&self, pos: Pos, drops: &mut EventWriter<DropItem>) {
+        for item in self.items.iter() {
+            drops.send(DropItem { item: *item, pos });
+        }
+    }
+}
+
+// takes up to `count` from a stack on the ground into `by`'s inventory, as much as they can carry.
+// Tops up a stack of the same thing they're already holding
+#[derive(Event)]
+pub struct PickUpItem {
+    pub item: Entity,
+    pub by: Entity,
+    pub count: u32,
+}
+
+// uses up `count` of a stack, carried or not. Anything more than the stack holds is ignored
+#[derive(Event)]
+pub struct ConsumeItem {
+    pub item: Entity,
+    pub count: u32,
+}
+
+// puts a carried item back on the ground at `pos`, merging it into a matching stack there or
+// spilling onto the closest free tile
+#[derive(Event)]
+pub struct DropItem {
+    pub item: Entity,
+    pub pos: Pos,
+}
+
+// drops `count` of an item on a tile. Tops up stacks of the same thing already there first, and
+// spills whatever doesn't fit onto the closest empty tiles
+#[derive(Event)]
+pub struct SpawnItem {
+    pub def: String,
+    pub count: u32,
+    pub pos: Pos,
+}
+
+fn load_item_registry(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let mut defs = HashMap::new();
+    for pack in ITEM_PACKS {
+        let pack: Vec<ItemDef> = serde_json::from_str(pack).expect("invalid item data");
+        for def in pack {
+            if defs.contains_key(&def.id) {
+                panic!("item {} is defined twice", def.id);
+            }
+            defs.insert(def.id.clone(), def);
+        }
+    }
+
+    let mut textures = HashMap::new();
+    for def in defs.values() {
+        textures
+            .entry(def.sprite.clone())
+            .or_insert_with(|| asset_server.load(def.sprite.clone()));
+    }
+
+    commands.insert_resource(ItemRegistry { defs, textures });
+}
+
+fn spawn_stack(commands: &mut Commands, registry: &ItemRegistry, def: &ItemDef, count: u32, rot: f32, pos: Pos) -> Entity {
+    commands
+        .spawn((
+            Item {
+                def: def.id.clone(),
+                count,
+                rot,
+            },
+            pos,
+            SpriteBundle {
+                texture: registry.textures[&def.sprite].clone(),
+                transform: Transform::from_xyz((pos.0 * SPRITE_SIZE) as f32, (pos.1 * SPRITE_SIZE) as f32, ITEM_Z)
+                    .with_scale(Vec3::splat(ITEM_SCALE)),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text2dBundle {
+                    text: Text::from_section("", TextStyle { font_size: 14.0, ..default() }),
+                    text_anchor: Anchor::BottomRight,
+                    transform: Transform::from_xyz(SPRITE_SIZE as f32 / 2.0, -(SPRITE_SIZE as f32) / 2.0, 1.0),
+                    ..default()
+                },
+                ItemCountText,
+            ));
+        })
+        .id()
+}
+
+// the closest tile to `pos` with nothing on it and no wall over it, going outwards ring by ring
+pub fn free_tile(pos: Pos, taken: &[Pos], terrain: &TerrainMap) -> Option<Pos> {
+    (0..=MAX_SPILL_RADIUS).find_map(|radius| {
+        (-radius..=radius)
+            .flat_map(|x| (-radius..=radius).map(move |y| Pos(pos.0 + x, pos.1 + y)))
+            .filter(|p| p.0.abs_diff(pos.0) as i32 == radius || p.1.abs_diff(pos.1) as i32 == radius)
+            .find(|p| !taken.contains(p) && terrain.structures.get(p) != Some(&StructureKind::Wall))
+    })
+}
+
+fn spawn_items(
+    mut commands: Commands,
+    mut events: EventReader<SpawnItem>,
+    registry: Res<ItemRegistry>,
+    terrain: Res<TerrainMap>,
+    mut query: Query<(&mut Item, &Pos)>,
+) {
+    // tiles stacks were spawned on this frame, which the query doesn't know about yet
+    let mut spawned: Vec<Pos> = vec![];
+
+    for event in events.read() {
+        let Some(def) = registry.get(&event.def) else {
+            screen_print!(push, sec: 5.0, "Unknown item {}", event.def);
+            continue;
+        };
+        let mut remaining = event.count;
+
+        for (mut item, _) in query.iter_mut().filter(|(item, pos)| item.def == def.id && **pos == event.pos) {
+            let added = remaining.min(def.stack_size.saturating_sub(item.count));
+            item.absorb(added, 0.0);
+            remaining -= added;
+        }
+
+        while remaining > 0 {
+            let taken: Vec<Pos> = query.iter().map(|(_, pos)| *pos).chain(spawned.iter().copied()).collect();
+            let Some(pos) = free_tile(event.pos, &taken, &terrain) else {
+                // nowhere to put it, the rest is lost
+                break;
+            };
+            let count = remaining.min(def.stack_size);
+            spawn_stack(&mut commands, &registry, def, count, 0.0, pos);
+            spawned.push(pos);
+            remaining -= count;
+        }
+    }
+}
+
+pub fn drop_items(
+    mut commands: Commands,
+    mut events: EventReader<DropItem>,
+    registry: Res<ItemRegistry>,
+    terrain: Res<TerrainMap>,
+    mut carried: Query<(&mut Item, &Carried, &mut Transform)>,
+    mut ground: Query<(&mut Item, &Pos), Without<Carried>>,
+    mut carriers: Query<&mut Inventory>,
+) {
+    // tiles items were put on this frame, which the query doesn't know about yet
+    let mut placed: Vec<Pos> = vec![];
+
+    for event in events.read() {
+        let Ok((mut item, carried, mut transform)) = carried.get_mut(event.item) else {
+            continue;
+        };
+        let Some(def) = registry.get(&item.def) else {
+            continue;
+        };
+        // the carrier may be gone already
+        if let Ok(mut inventory) = carriers.get_mut(carried.by) {
+            inventory.items.retain(|i| *i != event.item);
+            inventory.weight = (inventory.weight - item.count as f32 * def.weight).max(0.0);
+        }
+        let stack_size = def.stack_size;
+        let def = item.def.clone();
+
+        for (mut stack, _) in ground.iter_mut().filter(|(stack, pos)| stack.def == def && **pos == event.pos) {
+            let added = item.count.min(stack_size.saturating_sub(stack.count));
+            stack.absorb(added, item.rot);
+            item.count -= added;
+        }
+        if item.count == 0 {
+            commands.entity(event.item).despawn_recursive();
+            continue;
+        }
+
+        let taken: Vec<Pos> = ground.iter().map(|(_, pos)| *pos).chain(placed.iter().copied()).collect();
+        // nowhere to put it, so it stays where it is on top of something else
+        let pos = free_tile(event.pos, &taken, &terrain).unwrap_or(event.pos);
+        transform.translation = Vec3::new((pos.0 * SPRITE_SIZE) as f32, (pos.1 * SPRITE_SIZE) as f32, ITEM_Z);
+        transform.scale = Vec3::splat(ITEM_SCALE);
+        commands.entity(event.item).remove::<Carried>().insert(pos);
+        placed.push(pos);
+    }
+}
+
+// a wall went up over a stack, so it's pushed out to the closest free tile where someone can still get to it
+fn clear_walled_tiles(
+    mut edits: EventReader<EditTile>,
+    terrain: Res<TerrainMap>,
+    mut ground: Query<(&mut Pos, &mut Transform), (With<Item>, Without<Carried>)>,
+) {
+    let walled: Vec<Pos> = edits
+        .read()
+        .filter(|edit| edit.structure == StructureKind::Wall && !edit.removed)
+        .map(|edit| edit.pos)
+        .collect();
+    if walled.is_empty() {
+        return;
+    }
+
+    let mut taken: Vec<Pos> = ground.iter().map(|(pos, _)| *pos).collect();
+    for (mut pos, mut transform) in ground.iter_mut().filter(|(pos, _)| walled.contains(pos)) {
+        // walled in on every side, nothing to be done
+        let Some(free) = free_tile(*pos, &taken, &terrain) else {
+            continue;
+        };
+        *pos = free;
+        transform.translation = Vec3::new((free.0 * SPRITE_SIZE) as f32, (free.1 * SPRITE_SIZE) as f32, ITEM_Z);
+        taken.push(free);
+    }
+}
+
+fn pick_up_items(
+    mut commands: Commands,
+    mut events: EventReader<PickUpItem>,
+    registry: Res<ItemRegistry>,
+    mut carriers: Query<&mut Inventory>,
+    mut ground: Query<(&mut Item, &Pos), Without<Carried>>,
+    mut held: Query<&mut Item, With<Carried>>,
+) {
+    // whole stacks picked up this frame, which are still on the ground as far as the query knows
+    let mut taken: Vec<Entity> = vec![];
+
+    for event in events.read() {
+        if taken.contains(&event.item) {
+            continue;
+        }
+        let Ok(mut inventory) = carriers.get_mut(event.by) else {
+            continue;
+        };
+        // gone, or someone else has it already
+        let Ok((mut item, pos)) = ground.get_mut(event.item) else {
+            continue;
+        };
+        let Some(def) = registry.get(&item.def) else {
+            continue;
+        };
+        let fits = if def.weight > 0.0 { (inventory.free_weight() / def.weight) as u32 } else { u32::MAX };
+        let count = event.count.min(item.count).min(fits);
+        if count == 0 {
+            continue;
+        }
+        inventory.weight += count as f32 * def.weight;
+
+        let same = inventory
+            .items
+            .iter()
+            .copied()
+            .find(|e| held.get(*e).is_ok_and(|h| h.def == item.def && h.count + count <= def.stack_size));
+        if let Some(same) = same {
+            held.get_mut(same).unwrap().absorb(count, item.rot);
+            item.count -= count;
+            if item.count == 0 {
+                commands.entity(event.item).despawn_recursive();
+                taken.push(event.item);
+            }
+        } else if count == item.count {
+            commands.entity(event.item).remove::<Pos>().insert(Carried { by: event.by });
+            inventory.items.push(event.item);
+            taken.push(event.item);
+        } else {
+            // splits the stack, and carries the new one
+            item.count -= count;
+            let split = spawn_stack(&mut commands, &registry, def, count, item.rot, *pos);
+            commands.entity(split).remove::<Pos>().insert(Carried { by: event.by });
+            inventory.items.push(split);
+        }
+    }
+}
+
+fn consume_items(
+    mut commands: Commands,
+    mut events: EventReader<ConsumeItem>,
+    registry: Res<ItemRegistry>,
+    mut items: Query<(&mut Item, Option<&Carried>)>,
+    mut carriers: Query<&mut Inventory>,
+) {
+    for event in events.read() {
+        let Ok((mut item, carried)) = items.get_mut(event.item) else {
+            continue;
+        };
+        let count = event.count.min(item.count);
+        item.count -= count;
+
+        let carrier = carried.and_then(|carried| carriers.get_mut(carried.by).ok());
+        if let Some(mut inventory) = carrier {
+            let weight = registry.get(&item.def).map_or(0.0, |def| def.weight);
+            inventory.weight = (inventory.weight - count as f32 * weight).max(0.0);
+            if item.count == 0 {
+                inventory.items.retain(|i| *i != event.item);
+            }
+        }
+        if item.count == 0 {
+            commands.entity(event.item).despawn_recursive();
+        }
+    }
+}
+
+// carried items go wherever the carrier goes, and fall to the ground if the carrier is gone
+fn follow_carrier(
+    mut items: Query<(Entity, &Carried, &mut Transform)>,
+    carriers: Query<(&Transform, &Inventory), Without<Carried>>,
+    mut drops: EventWriter<DropItem>,
+) {
+    for (entity, carried, mut transform) in items.iter_mut() {
+        match carriers.get(carried.by) {
+            Ok((carrier, inventory)) => {
+                let slot = inventory.items.iter().position(|i| *i == entity).unwrap_or(0);
+                transform.translation = Vec3::new(
+                    carrier.translation.x + slot as f32 * CARRIED_SPACING,
+                    carrier.translation.y + CARRIED_OFFSET,
+                    CARRIED_Z,
+                );
+                transform.scale = Vec3::splat(CARRIED_SCALE);
+            }
+            Err(_) => {
+                drops.send(DropItem {
+                    item: entity,
+                    pos: Pos::from_translation(transform.translation),
+                });
+            }
+        }
+    }
+}
+
+fn spawn_starting_items(mut events: EventWriter<SpawnItem>) {
+    for (def, count) in STARTING_ITEMS {
+        events.send(SpawnItem {
+            def: def.to_string(),
+            count,
+            pos: STARTING_ITEMS_POS,
+        });
+    }
+}
+
+fn update_count_text(items: Query<(&Item, &Children), Changed<Item>>, mut texts: Query<&mut Text, With<ItemCountText>>) {
+    for (item, children) in items.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = if item.count > 1 { item.count.to_string() } else { String::new() };
+            }
+        }
+    }
+}
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnItem>()
+            .add_event::<PickUpItem>()
+            .add_event::<ConsumeItem>()
+            .add_event::<DropItem>()
+            .add_systems(Startup, load_item_registry)
+            .add_systems(OnEnter(InGame), spawn_starting_items)
+            .add_systems(Update, spawn_items.run_if(in_state(InGame)))
+            .add_systems(Update, follow_carrier.run_if(in_state(InGame)))
+            .add_systems(Update, drop_items.after(follow_carrier).run_if(in_state(InGame)))
+            .add_systems(Update, pick_up_items.after(drop_items).run_if(in_state(InGame)))
+            .add_systems(Update, consume_items.after(pick_up_items).run_if(in_state(InGame)))
+            .add_systems(Update, clear_walled_tiles.after(edit_tiles).run_if(in_state(InGame)))
+            .add_systems(
+                Update,
+                update_count_text
+                    .after(spawn_items)
+                    .after(drop_items)
+                    .after(pick_up_items)
+                    .after(consume_items)
+                    .run_if(in_state(InGame)),
+            );
+    }
+}
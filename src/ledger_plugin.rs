@@ -0,0 +1,224 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::AppState::InGame;
+use crate::clock_plugin::GameClock;
+use crate::construction_plugin::{Structure, StructureRegistry};
+use crate::item_plugin::{Carried, Item, ItemCategory, ItemRegistry};
+use crate::pathing::Pos;
+use crate::stockpile_plugin::Stockpile;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ItemTotals {
+    pub stockpiled: u32,
+    // lying around outside a stockpile that wants it, or being carried
+    pub loose: u32,
+}
+
+impl ItemTotals {
+    pub fn total(&self) -> u32 {
+        self.stockpiled + self.loose
+    }
+}
+
+// how much of something turned up and how much got used up, eaten or spoiled over a day
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Flow {
+    pub produced: u32,
+    pub consumed: u32,
+}
+
+// what a stack was counted as last time it changed
+struct Counted {
+    def: String,
+    count: u32,
+    stockpiled: bool,
+}
+
+// everything the colony owns. Only stacks and buildings that changed are recounted, so it's cheap to
+// keep up to date and to ask
+#[derive(Resource, Default)]
+pub struct ColonyLedger {
+    pub items: HashMap<String, ItemTotals>,
+    pub item_wealth: f32,
+    pub building_wealth: f32,
+    // since midnight, and over the whole of the day before
+    pub today: HashMap<String, Flow>,
+    pub yesterday: HashMap<String, Flow>,
+    stacks: HashMap<Entity, Counted>,
+    buildings: HashMap<Entity, f32>,
+    day: u32,
+}
+
+impl ColonyLedger {
+    pub fn totals(&self, item: &str) -> ItemTotals {
+        self.items.get(item).copied().unwrap_or_default()
+    }
+
+    pub fn total(&self, item: &str) -> u32 {
+        self.totals(item).total()
+    }
+
+    pub fn wealth(&self) -> f32 {
+        self.item_wealth + self.building_wealth
+    }
+
+    // adds `count` of `def` to the totals, or takes it away with `sign` -1
+    fn tally(&mut self, def: &str, count: u32, stockpiled: bool, sign: i64, registry: &ItemRegistry) {
+        let totals = self.items.entry(def.to_string()).or_default();
+        let bucket = if stockpiled { &mut totals.stockpiled } else { &mut totals.loose };
+        *bucket = (*bucket as i64 + sign * count as i64).max(0) as u32;
+        let value = registry.get(def).map_or(0.0, |def| def.value);
+        self.item_wealth += sign as f32 * count as f32 * value;
+    }
+}
+
+fn stockpiled(pos: Option<&Pos>, category: ItemCategory, stockpiles: &Query<&Stockpile>) -> bool {
+    pos.is_some_and(|pos| stockpiles.iter().any(|s| s.rect.contains(*pos) && s.accepts(category)))
+}
+
+// recounts the stacks that changed this frame. Runs after Update so everything spawned, merged, split
+// and despawned there has landed, and moving things between stacks nets out instead of counting as
+// production and consumption
+fn update_ledger(
+    mut ledger: ResMut<ColonyLedger>,
+    registry: Res<ItemRegistry>,
+    clock: Res<GameClock>,
+    changed: Query<Entity, Or<(Changed<Item>, Changed<Pos>, Changed<Carried>)>>,
+    items: Query<(Entity, &Item, Option<&Pos>)>,
+    stockpiles: Query<&Stockpile>,
+    changed_stockpiles: Query<(), Changed<Stockpile>>,
+    mut removed_stockpiles: RemovedComponents<Stockpile>,
+    mut removed: RemovedComponents<Item>,
+) {
+    if clock.day != ledger.day {
+        ledger.yesterday = std::mem::take(&mut ledger.today);
+        ledger.day = clock.day;
+    }
+
+    // a stockpile going down or changing what it takes can change where everything counts as stored
+    let restock = !changed_stockpiles.is_empty() || removed_stockpiles.read().count() > 0;
+    let recount: Vec<Entity> = if restock { items.iter().map(|(entity, _, _)| entity).collect() } else { changed.iter().collect() };
+
+    let mut net: HashMap<String, i64> = HashMap::new();
+    for entity in recount {
+        let Ok((_, item, pos)) = items.get(entity) else {
+            continue;
+        };
+        let Some(def) = registry.get(&item.def) else {
+            continue;
+        };
+        let now = Counted {
+            def: item.def.clone(),
+            count: item.count,
+            stockpiled: stockpiled(pos, def.category, &stockpiles),
+        };
+        ledger.tally(&now.def, now.count, now.stockpiled, 1, &registry);
+        *net.entry(now.def.clone()).or_insert(0) += now.count as i64;
+
+        if let Some(before) = ledger.stacks.insert(entity, now) {
+            ledger.tally(&before.def, before.count, before.stockpiled, -1, &registry);
+            *net.entry(before.def).or_insert(0) -= before.count as i64;
+        }
+    }
+    for entity in removed.read() {
+        if let Some(before) = ledger.stacks.remove(&entity) {
+            ledger.tally(&before.def, before.count, before.stockpiled, -1, &registry);
+            *net.entry(before.def).or_insert(0) -= before.count as i64;
+        }
+    }
+
+    for (def, change) in net.into_iter().filter(|(_, change)| *change != 0) {
+        let flow = ledger.today.entry(def).or_default();
+        if change > 0 {
+            flow.produced += change as u32;
+        } else {
+            flow.consumed += (-change) as u32;
+        }
+    }
+}
+
+// what went into building something counts towards the colony's wealth for as long as it stands
+fn update_building_wealth(
+    mut ledger: ResMut<ColonyLedger>,
+    added: Query<(Entity, &Structure), Added<Structure>>,
+    mut removed: RemovedComponents<Structure>,
+    structures: Res<StructureRegistry>,
+    registry: Res<ItemRegistry>,
+) {
+    for (entity, structure) in added.iter() {
+        let Some(def) = structures.get(&structure.def) else {
+            continue;
+        };
+        let value: f32 = def
+            .materials
+            .iter()
+            .map(|cost| cost.count as f32 * registry.get(&cost.item).map_or(0.0, |item| item.value))
+            .sum();
+        ledger.building_wealth += value;
+        ledger.buildings.insert(entity, value);
+    }
+    for entity in removed.read() {
+        if let Some(value) = ledger.buildings.remove(&entity) {
+            ledger.building_wealth -= value;
+        }
+    }
+}
+
+fn resource_bar_ui(mut contexts: EguiContexts, ledger: Res<ColonyLedger>, registry: Res<ItemRegistry>) {
+    egui::TopBottomPanel::top("resource_bar").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label(format!("Wealth {:.0}", ledger.wealth()))
+                .on_hover_text(format!("Items {:.0}\nBuildings {:.0}", ledger.item_wealth, ledger.building_wealth));
+
+            for category in ItemCategory::iter() {
+                let mut items: Vec<(&str, &ItemTotals)> = ledger
+                    .items
+                    .iter()
+                    .filter(|(_, totals)| totals.total() > 0)
+                    .filter_map(|(id, totals)| registry.get(id).filter(|def| def.category == category).map(|def| (def.name.as_str(), totals)))
+                    .collect();
+                items.sort_by_key(|(name, _)| *name);
+
+                let total: u32 = items.iter().map(|(_, totals)| totals.total()).sum();
+                let details: Vec<String> = items
+                    .iter()
+                    .map(|(name, totals)| format!("{}: {} ({} stockpiled)", name, totals.total(), totals.stockpiled))
+                    .collect();
+
+                ui.separator();
+                let label = ui.label(format!("{} {}", category.as_ref(), total));
+                if !details.is_empty() {
+                    label.on_hover_text(details.join("\n"));
+                }
+            }
+
+            ui.separator();
+            let mut flows: Vec<String> = registry
+                .defs
+                .values()
+                .filter_map(|def| ledger.yesterday.get(&def.id).map(|flow| (def, flow)))
+                .map(|(def, flow)| format!("{}: +{} -{}", def.name, flow.produced, flow.consumed))
+                .collect();
+            flows.sort();
+            let label = ui.label("Yesterday");
+            if !flows.is_empty() {
+                label.on_hover_text(flows.join("\n"));
+            }
+        });
+    });
+}
+
+pub struct LedgerPlugin;
+
+impl Plugin for LedgerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ColonyLedger>()
+            .add_systems(PostUpdate, update_ledger.run_if(in_state(InGame)))
+            .add_systems(PostUpdate, update_building_wealth.run_if(in_state(InGame)))
+            .add_systems(Update, resource_bar_ui.run_if(in_state(InGame)));
+    }
+}
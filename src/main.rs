@@ -3,34 +3,74 @@ use bevy::prelude::*;
 use bevy::window::PresentMode;
 use bevy_asset_loader::prelude::AssetCollection;
 use bevy_debug_text_overlay::OverlayPlugin;
+use bevy_egui::EguiPlugin;
 use bevy_enum_filter::prelude::*;
 use bevy_fast_tilemap::FastTileMapPlugin;
 use bevy_framepace::{FramepaceSettings, Limiter};
 use bevy_pancam::{PanCam, PanCamPlugin};
 use iyes_perf_ui::{PerfUiCompleteBundle, PerfUiPlugin};
 
+use crate::bed_plugin::BedPlugin;
 use crate::character_plugin::CharacterPlugin;
+use crate::clock_plugin::ClockPlugin;
+use crate::construction_plugin::ConstructionPlugin;
+use crate::crafting_plugin::CraftingPlugin;
 use crate::debug_plugin::DebugPlugin;
+use crate::decision_log_plugin::DecisionLogPlugin;
+use crate::farming_plugin::FarmingPlugin;
 use crate::growth_plugin::PlanGrowthPlugin;
+use crate::health_plugin::HealthPlugin;
 use crate::input_plugin::InputPlugin;
+use crate::item_plugin::ItemPlugin;
+use crate::ledger_plugin::LedgerPlugin;
+use crate::mood_plugin::MoodPlugin;
 use crate::name_plugin::NamePlugin;
+use crate::schedule_plugin::SchedulePlugin;
+use crate::reservation_plugin::ReservationPlugin;
+use crate::social_plugin::SocialPlugin;
+use crate::spoilage_plugin::SpoilagePlugin;
+use crate::stockpile_plugin::StockpilePlugin;
 use crate::task_scorer::TaskScoringPlugin;
 use crate::tasks::{AllTasks, BasicTasksPlugin};
+use crate::temperature_plugin::TemperaturePlugin;
+use crate::traits_plugin::TraitsPlugin;
 #[allow(unused)]
 //use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use crate::wander_plugin::RandomMovementPlugin;
+use crate::weather_plugin::WeatherPlugin;
+use crate::work_plugin::WorkPlugin;
 use crate::world_gen_plugin::WorldGenPlugin;
 
+mod bed_plugin;
 mod character_plugin;
+mod clock_plugin;
+mod construction_plugin;
+mod crafting_plugin;
 mod debug_plugin;
+mod decision_log_plugin;
+mod farming_plugin;
 mod growth_plugin;
+mod health_plugin;
+mod item_plugin;
+mod ledger_plugin;
+mod mood_plugin;
 mod name_plugin;
 mod pathing;
+mod reservation_plugin;
+mod schedule_plugin;
+mod skills;
+mod social_plugin;
+mod spoilage_plugin;
+mod stockpile_plugin;
 mod task_scorer;
 mod tasks;
+mod temperature_plugin;
+mod traits_plugin;
 mod wander_plugin;
+mod weather_plugin;
 mod world_gen_plugin;
 mod input_plugin;
+mod work_plugin;
 
 #[allow(unused)]
 #[derive(Default, States, Debug, Clone, Eq, PartialEq, Hash)]
@@ -141,6 +181,8 @@ fn main() {
             //WorldInspectorPlugin::new(),
         ))
         .add_plugins(InputPlugin)
+        .add_plugins((EguiPlugin, WorkPlugin, DecisionLogPlugin, HealthPlugin, MoodPlugin, TraitsPlugin, SocialPlugin, ClockPlugin, SchedulePlugin, WeatherPlugin, TemperaturePlugin))
+        .add_plugins((ReservationPlugin, ItemPlugin, FarmingPlugin, StockpilePlugin, ConstructionPlugin, BedPlugin, CraftingPlugin, SpoilagePlugin, LedgerPlugin))
         .add_plugins((DebugPlugin, bevy_framepace::FramepacePlugin, PerfUiPlugin, FastTileMapPlugin::default(), ))
         .add_systems(Startup, setup)
         .add_systems(OnEnter(AppState::Loading), load_textures)
@@ -151,6 +193,8 @@ fn main() {
 
 fn setup(mut commands: Commands, mut framepace: ResMut<FramepaceSettings>) {
     commands.spawn(Camera2dBundle::default()).insert(PanCam {
+        // left and right click belong to the tools, dragging with them mustn't move the map too
+        grab_buttons: vec![MouseButton::Middle],
         min_scale: 0.1,
         max_scale: Some(30.0),
         ..default()
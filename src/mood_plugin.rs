@@ -0,0 +1,320 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy_debug_text_overlay::screen_print;
+use bevy_egui::{egui, EguiContexts};
+use rand::{thread_rng, Rng};
+use strum_macros::AsRefStr;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::growth_plugin::Plant;
+use crate::health_plugin::{Collapsed, ColonistDied, Corpse};
+use crate::name_plugin::Name;
+use crate::pathing::Pos;
+use crate::task_scorer::Busy;
+use crate::tasks::*;
+use crate::traits_plugin::{TraitRegistry, Traits};
+use crate::wander_plugin::stop_moving;
+use crate::weather_plugin::Weather;
+use crate::world_gen_plugin::{Indoors, SPRITE_SIZE};
+
+const BASE_MOOD: f32 = 50.0;
+// how fast the displayed mood moves towards what the colonist is currently feeling, per second
+const MOOD_CHANGE_RATE: f32 = 2.0;
+
+// below this, work gets slower
+const LOW_MOOD: f32 = 35.0;
+// below this, colonists may snap and stop following orders for a while
+const BREAK_MOOD: f32 = 20.0;
+// chance per second of a mental break at 0 mood, scaled down linearly up to BREAK_MOOD
+const BREAK_CHANCE: f32 = 0.05;
+const BREAK_DURATION: f32 = 30.0;
+
+const BEAUTY_RADIUS: f32 = 3.0 * SPRITE_SIZE as f32;
+const MAX_BEAUTY: f32 = 6.0;
+const CORPSE_RADIUS: f32 = 5.0 * SPRITE_SIZE as f32;
+pub const WITNESS_RADIUS: f32 = 8.0 * SPRITE_SIZE as f32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+pub enum ThoughtKind {
+    AteMeal,
+    AteRawFood,
+    SleptOutside,
+    SleptInBed,
+    SawDeath,
+    ColonistDied,
+    HadChat,
+    HadArgument,
+    LostFriend,
+}
+
+impl ThoughtKind {
+    pub fn mood(&self) -> f32 {
+        match self {
+            ThoughtKind::AteMeal => 4.0,
+            ThoughtKind::AteRawFood => -2.0,
+            ThoughtKind::SleptOutside => -4.0,
+            ThoughtKind::SleptInBed => 3.0,
+            ThoughtKind::SawDeath => -15.0,
+            ThoughtKind::ColonistDied => -6.0,
+            ThoughtKind::HadChat => 3.0,
+            ThoughtKind::HadArgument => -5.0,
+            ThoughtKind::LostFriend => -12.0,
+        }
+    }
+
+    // in seconds
+    pub fn duration(&self) -> f32 {
+        match self {
+            ThoughtKind::AteMeal => 60.0,
+            ThoughtKind::AteRawFood => 60.0,
+            ThoughtKind::SleptOutside => 120.0,
+            ThoughtKind::SleptInBed => 120.0,
+            ThoughtKind::SawDeath => 300.0,
+            ThoughtKind::ColonistDied => 240.0,
+            ThoughtKind::HadChat => 60.0,
+            ThoughtKind::HadArgument => 90.0,
+            ThoughtKind::LostFriend => 400.0,
+        }
+    }
+}
+
+pub struct Thought {
+    pub kind: ThoughtKind,
+    // how strongly it's felt, e.g. how comfortable the bed was
+    pub strength: f32,
+    pub remaining: f32,
+}
+
+impl Thought {
+    // thoughts fade out as they get older
+    pub fn mood(&self) -> f32 {
+        self.kind.mood() * self.strength * self.remaining / self.kind.duration()
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Thoughts {
+    pub thoughts: Vec<Thought>,
+}
+
+#[derive(Component)]
+pub struct Mood {
+    pub value: f32,
+    // what the mood is heading towards, broken down for the UI
+    pub target: f32,
+    pub needs: f32,
+    pub environment: f32,
+    pub weather: f32,
+    pub traits: f32,
+}
+
+impl Default for Mood {
+    fn default() -> Self {
+        Mood {
+            value: BASE_MOOD,
+            target: BASE_MOOD,
+            needs: 0.0,
+            environment: 0.0,
+            weather: 0.0,
+            traits: 0.0,
+        }
+    }
+}
+
+impl Mood {
+    // multiplier for how fast the colonist gets work done
+    pub fn work_speed(&self) -> f32 {
+        if self.value >= LOW_MOOD {
+            1.0
+        } else {
+            0.5 + 0.5 * self.value.max(0.0) / LOW_MOOD
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct MentalBreak {
+    pub remaining: f32,
+}
+
+#[derive(Event)]
+pub struct AddThought {
+    pub entity: Entity,
+    pub kind: ThoughtKind,
+    // 1.0 for the thought's usual mood
+    pub strength: f32,
+}
+
+// -8 when empty, +8 when full
+fn need_mood(value: f32) -> f32 {
+    (value - 50.0) / 50.0 * 8.0
+}
+
+fn add_thoughts(mut events: EventReader<AddThought>, mut query: Query<&mut Thoughts>) {
+    for event in events.read() {
+        let Ok(mut thoughts) = query.get_mut(event.entity) else {
+            continue;
+        };
+
+        // the same thought doesn't stack, it just gets refreshed
+        match thoughts.thoughts.iter_mut().find(|t| t.kind == event.kind) {
+            Some(thought) => {
+                thought.strength = event.strength;
+                thought.remaining = event.kind.duration();
+            }
+            None => thoughts.thoughts.push(Thought {
+                kind: event.kind,
+                strength: event.strength,
+                remaining: event.kind.duration(),
+            }),
+        }
+    }
+}
+
+fn decay_thoughts(time: Res<Time>, mut query: Query<&mut Thoughts>) {
+    for mut thoughts in query.iter_mut() {
+        for thought in thoughts.thoughts.iter_mut() {
+            thought.remaining -= time.delta_seconds();
+        }
+        thoughts.thoughts.retain(|t| t.remaining > 0.0);
+    }
+}
+
+fn witness_deaths(
+    mut died: EventReader<ColonistDied>,
+    mut thoughts: EventWriter<AddThought>,
+    query: Query<(Entity, &Transform), With<Character>>,
+) {
+    for death in died.read() {
+        for (entity, transform) in query.iter() {
+            if entity == death.entity {
+                continue;
+            }
+
+            let kind = match transform.translation.truncate().distance(death.translation.truncate()) {
+                d if d <= WITNESS_RADIUS => ThoughtKind::SawDeath,
+                _ => ThoughtKind::ColonistDied,
+            };
+            thoughts.send(AddThought { entity, kind, strength: 1.0 });
+        }
+    }
+}
+
+fn update_mood(
+    time: Res<Time>,
+    mut query: Query<(&mut Mood, &Thoughts, &Traits, &Transform, &Hunger, &Thirst, &Sleep), With<Character>>,
+    plants: Query<&Transform, With<Plant>>,
+    corpses: Query<&Transform, With<Corpse>>,
+    trait_registry: Res<TraitRegistry>,
+    weather: Res<Weather>,
+    indoors: Res<Indoors>,
+) {
+    for (mut mood, thoughts, traits, transform, hunger, thirst, sleep) in query.iter_mut() {
+        let pos = transform.translation.truncate();
+
+        mood.needs = need_mood(hunger.value) + need_mood(thirst.value) + need_mood(sleep.value);
+
+        let beauty = plants
+            .iter()
+            .filter(|t| t.translation.truncate().distance(pos) <= BEAUTY_RADIUS)
+            .count() as f32;
+        let gore = corpses
+            .iter()
+            .filter(|t| t.translation.truncate().distance(pos) <= CORPSE_RADIUS)
+            .count() as f32;
+        mood.environment = beauty.min(MAX_BEAUTY) - gore * 10.0;
+
+        // the same rooms that keep the food dry and the warmth in keep the weather out
+        let inside = indoors.contains(Pos::from_translation(transform.translation));
+        mood.weather = if inside { 0.0 } else { weather.outdoor_mood() };
+        mood.traits = trait_registry.sum(traits, |t| t.mood);
+
+        let thought_mood: f32 = thoughts.thoughts.iter().map(|t| t.mood()).sum();
+        mood.target = (BASE_MOOD + mood.needs + mood.environment + mood.weather + mood.traits + thought_mood).clamp(0.0, 100.0);
+
+        let step = MOOD_CHANGE_RATE * time.delta_seconds();
+        mood.value += (mood.target - mood.value).clamp(-step, step);
+    }
+}
+
+fn start_mental_breaks(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<
+        (Entity, &Mood, &mut AllTasks, Option<&Name>),
+        (With<Character>, Without<MentalBreak>, Without<Collapsed>),
+    >,
+) {
+    let mut rng = thread_rng();
+
+    for (entity, mood, mut task, name) in query.iter_mut() {
+        if mood.value >= BREAK_MOOD {
+            continue;
+        }
+
+        let chance = BREAK_CHANCE * (1.0 - mood.value / BREAK_MOOD) * time.delta_seconds();
+        if !rng.gen_bool(chance.clamp(0.0, 1.0) as f64) {
+            continue;
+        }
+
+        if let Some(name) = name {
+            screen_print!(push, sec: 5.0, "{} is having a mental break", &name.0);
+        }
+
+        // wander around in a daze. Busy keeps the scorer from overriding it
+        *task = AllTasks::Wander;
+        stop_moving(commands.entity(entity).insert((Busy, MentalBreak { remaining: BREAK_DURATION })));
+    }
+}
+
+fn end_mental_breaks(mut commands: Commands, time: Res<Time>, mut query: Query<(Entity, &mut MentalBreak)>) {
+    for (entity, mut mental_break) in query.iter_mut() {
+        mental_break.remaining -= time.delta_seconds();
+        if mental_break.remaining <= 0.0 {
+            commands.entity(entity).remove::<(MentalBreak, Busy)>();
+        }
+    }
+}
+
+fn mood_ui(
+    mut contexts: EguiContexts,
+    query: Query<(&Name, &Mood, &Thoughts, &Traits, Option<&MentalBreak>), With<Character>>,
+    trait_registry: Res<TraitRegistry>,
+) {
+    egui::Window::new("Mood")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for (name, mood, thoughts, traits, mental_break) in query.iter() {
+                let header = match mental_break {
+                    Some(_) => format!("{}: {:.0} (mental break)", name.0, mood.value),
+                    None => format!("{}: {:.0}", name.0, mood.value),
+                };
+                ui.collapsing(header, |ui| {
+                    ui.label(format!("Needs: {:+.1}", mood.needs));
+                    ui.label(format!("Environment: {:+.1}", mood.environment));
+                    ui.label(format!("Weather: {:+.1}", mood.weather));
+                    let trait_names: Vec<&str> = trait_registry.defs(traits).map(|t| t.name.as_str()).collect();
+                    ui.label(format!("Traits ({}): {:+.1}", trait_names.join(", "), mood.traits));
+                    for thought in thoughts.thoughts.iter() {
+                        ui.label(format!("{}: {:+.1}", thought.kind.as_ref(), thought.mood()));
+                    }
+                });
+            }
+        });
+}
+
+pub struct MoodPlugin;
+
+impl Plugin for MoodPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AddThought>()
+            .add_systems(Update, add_thoughts.run_if(in_state(InGame)))
+            .add_systems(Update, decay_thoughts.run_if(in_state(InGame)))
+            .add_systems(Update, witness_deaths.run_if(in_state(InGame)))
+            .add_systems(Update, update_mood.run_if(in_state(InGame)))
+            .add_systems(Update, start_mental_breaks.run_if(in_state(InGame)))
+            .add_systems(Update, end_mental_breaks.run_if(in_state(InGame)))
+            .add_systems(Update, mood_ui.run_if(in_state(InGame)));
+    }
+}
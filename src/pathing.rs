@@ -3,16 +3,30 @@ use bevy::math::Vec3;
 use bevy::prelude::{Component, Reflect};
 use bevy::utils::HashMap;
 
+use crate::world_gen_plugin::SPRITE_SIZE;
+
+// the weight of a tile nothing can walk through, like a wall
+pub const IMPASSABLE: i32 = -1;
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Component, Reflect)]
 pub struct Pos(pub i32, pub i32);
 
 impl Pos {
+    // the tile a world position is on. Tiles are centered on multiples of SPRITE_SIZE
+    pub fn from_translation(translation: Vec3) -> Pos {
+        Pos(
+            (translation.x / SPRITE_SIZE as f32).round() as i32,
+            (translation.y / SPRITE_SIZE as f32).round() as i32,
+        )
+    }
+
     pub fn distance(&self, other: &Pos) -> u32 {
         (self.0.abs_diff(other.0) + self.1.abs_diff(other.1)) as u32
     }
 
     pub fn successors(&self, tile_weights: &HashMap<Pos, i32>) -> Vec<(Pos, u32)> {
         let &Pos(x, y) = self;
+        let blocked = |p: Pos| tile_weights.get(&p) == Some(&IMPASSABLE);
 
         vec![
             Pos(x + 1, y + 0),
@@ -25,6 +39,8 @@ impl Pos {
             Pos(x - 1, y - 1),
         ]
             .into_iter()
+            // walls block the tile and cutting the corners round it
+            .filter(|p| !blocked(*p) && !blocked(Pos(p.0, y)) && !blocked(Pos(x, p.1)))
             .map(|p| {
                 let weight = tile_weights.get(&p).unwrap_or(&9999);
                 (p, weight.to_owned() as u32)
@@ -0,0 +1,154 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::AppState::InGame;
+use crate::pathing::Pos;
+use crate::task_scorer::Busy;
+use crate::tasks::AllTasks;
+use crate::world_gen_plugin::EditTile;
+
+// seconds until a claim lapses once the job it was made for stops renewing it, in case whoever made it
+// never gets round to finishing
+const RESERVATION_TIME: f32 = 120.0;
+// seconds something nobody could find a way to is left alone for, unless the map changes first
+const UNREACHABLE_TIME: f32 = 30.0;
+
+// something a colonist can lay claim to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Target {
+    Entity(Entity),
+    Tile(Pos),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Reservation {
+    pub claimant: Entity,
+    // the claim only lasts as long as the claimant keeps doing this
+    pub task: AllTasks,
+    // in seconds since startup
+    pub expires: f32,
+}
+
+// who is going for what, so two colonists don't walk up to the same thing
+#[derive(Resource, Default)]
+pub struct Reservations {
+    targets: HashMap<Target, Vec<Reservation>>,
+    // targets someone failed to find a path to, and when to try them again
+    unreachable: HashMap<Target, f32>,
+}
+
+impl Reservations {
+    pub fn claimants(&self, target: Target) -> usize {
+        self.targets.get(&target).map_or(0, |claims| claims.len())
+    }
+
+    // nobody has claimed it, and nobody has just failed to get to it
+    pub fn is_free(&self, target: Target) -> bool {
+        self.claimants(target) == 0 && self.is_reachable(target)
+    }
+
+    pub fn is_reachable(&self, target: Target) -> bool {
+        !self.unreachable.contains_key(&target)
+    }
+
+    // stops anyone being sent to `target` again for a while, so they don't all keep pathing to it and failing
+    pub fn mark_unreachable(&mut self, target: Target, now: f32) {
+        self.unreachable.insert(target, now + UNREACHABLE_TIME);
+    }
+
+    // claimed by anyone other than `by`
+    pub fn is_reserved(&self, target: Target, by: Entity) -> bool {
+        self.targets
+            .get(&target)
+            .is_some_and(|claims| claims.iter().any(|r| r.claimant != by))
+    }
+
+    // claims `target` for `claimant` alone. Renews the claim if they already have it
+    pub fn reserve(&mut self, target: Target, claimant: Entity, task: AllTasks, now: f32) -> bool {
+        self.share(target, claimant, task, now, 1)
+    }
+
+    // claims `target` alongside at most `limit - 1` others, e.g. a stack of food several colonists can eat from
+    pub fn share(&mut self, target: Target, claimant: Entity, task: AllTasks, now: f32, limit: usize) -> bool {
+        let claims = self.targets.entry(target).or_default();
+        let expires = now + RESERVATION_TIME;
+
+        if let Some(claim) = claims.iter_mut().find(|r| r.claimant == claimant) {
+            claim.task = task;
+            claim.expires = expires;
+            return true;
+        }
+        if claims.len() >= limit {
+            return false;
+        }
+        claims.push(Reservation { claimant, task, expires });
+        true
+    }
+
+    // pushes back the expiry of a claim `claimant` already has
+    pub fn renew(&mut self, target: Target, claimant: Entity, now: f32) {
+        let claim = self.targets.get_mut(&target).and_then(|claims| claims.iter_mut().find(|r| r.claimant == claimant));
+        if let Some(claim) = claim {
+            claim.expires = now + RESERVATION_TIME;
+        }
+    }
+
+    pub fn release(&mut self, target: Target, claimant: Entity) {
+        if let Some(claims) = self.targets.get_mut(&target) {
+            claims.retain(|r| r.claimant != claimant);
+        }
+    }
+
+    fn retain(&mut self, mut keep: impl FnMut(&Reservation) -> bool) {
+        for claims in self.targets.values_mut() {
+            claims.retain(|r| keep(r));
+        }
+        self.targets.retain(|_, claims| !claims.is_empty());
+    }
+}
+
+// a job component whose claims last for as long as it's on the colonist
+pub trait Claims {
+    fn claimed(&self) -> Vec<Target>;
+}
+
+// keeps the claims of jobs that are still being worked on from lapsing, however long they take
+pub fn renew_claims<T: Component + Claims>(
+    time: Res<Time>,
+    mut reservations: ResMut<Reservations>,
+    query: Query<(Entity, &T)>,
+) {
+    let now = time.elapsed_seconds();
+    for (entity, job) in query.iter() {
+        for target in job.claimed() {
+            reservations.renew(target, entity, now);
+        }
+    }
+}
+
+// claims go away once the claimant finishes the task they made them for, gets pulled off it, dies, or
+// just takes too long. Runs before Update, so the previous frame's Busy changes have all landed and
+// nobody has claimed anything new yet
+fn release_reservations(time: Res<Time>, mut reservations: ResMut<Reservations>, claimants: Query<&AllTasks, With<Busy>>) {
+    let now = time.elapsed_seconds();
+    reservations.retain(|r| r.expires > now && claimants.get(r.claimant).is_ok_and(|task| *task == r.task));
+    reservations.unreachable.retain(|_, retry| *retry > now);
+}
+
+// anything built or taken down may have opened up a way to, or around, somewhere that couldn't be reached
+fn forget_unreachable(mut edits: EventReader<EditTile>, mut reservations: ResMut<Reservations>) {
+    if edits.read().count() > 0 {
+        reservations.unreachable.clear();
+    }
+}
+
+pub struct ReservationPlugin;
+
+impl Plugin for ReservationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Reservations>()
+            .add_systems(PreUpdate, release_reservations.run_if(in_state(InGame)))
+            .add_systems(Update, forget_unreachable.run_if(in_state(InGame)));
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter};
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::clock_plugin::GameClock;
+use crate::name_plugin::Name;
+
+// default day: sleep from 22:00 to 06:00, work 07:00 - 17:00, free time in the evening
+const DEFAULT_SLEEP: [u32; 8] = [22, 23, 0, 1, 2, 3, 4, 5];
+const DEFAULT_WORK: std::ops::Range<u32> = 7..17;
+const DEFAULT_RECREATION: std::ops::Range<u32> = 18..22;
+// nocturnal colonists get the same day shifted by this many hours
+const NOCTURNAL_SHIFT: usize = 12;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, EnumIter, AsRefStr)]
+pub enum ScheduleSlot {
+    #[default]
+    Anything,
+    Sleep,
+    Work,
+    Recreation,
+}
+
+impl ScheduleSlot {
+    fn next(&self) -> ScheduleSlot {
+        match self {
+            ScheduleSlot::Anything => ScheduleSlot::Sleep,
+            ScheduleSlot::Sleep => ScheduleSlot::Work,
+            ScheduleSlot::Work => ScheduleSlot::Recreation,
+            ScheduleSlot::Recreation => ScheduleSlot::Anything,
+        }
+    }
+
+    fn color(&self) -> egui::Color32 {
+        match self {
+            ScheduleSlot::Anything => egui::Color32::from_gray(70),
+            ScheduleSlot::Sleep => egui::Color32::from_rgb(50, 60, 140),
+            ScheduleSlot::Work => egui::Color32::from_rgb(150, 110, 40),
+            ScheduleSlot::Recreation => egui::Color32::from_rgb(50, 130, 60),
+        }
+    }
+}
+
+// what each hour of the day is meant for. Only a bias for the scorer, needs still win
+#[derive(Component)]
+pub struct Schedule {
+    pub hours: [ScheduleSlot; 24],
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        let mut hours = [ScheduleSlot::Anything; 24];
+        for hour in DEFAULT_SLEEP {
+            hours[hour as usize] = ScheduleSlot::Sleep;
+        }
+        for hour in DEFAULT_WORK {
+            hours[hour as usize] = ScheduleSlot::Work;
+        }
+        for hour in DEFAULT_RECREATION {
+            hours[hour as usize] = ScheduleSlot::Recreation;
+        }
+        Schedule { hours }
+    }
+}
+
+impl Schedule {
+    pub fn nocturnal() -> Self {
+        let mut schedule = Schedule::default();
+        schedule.hours.rotate_right(NOCTURNAL_SHIFT);
+        schedule
+    }
+
+    pub fn slot(&self, hour: u32) -> ScheduleSlot {
+        self.hours[hour as usize % 24]
+    }
+
+    pub fn cycle(&mut self, hour: usize) {
+        self.hours[hour] = self.hours[hour].next();
+    }
+}
+
+fn schedule_ui(
+    mut contexts: EguiContexts,
+    mut query: Query<(&Name, &mut Schedule), With<Character>>,
+    clock: Res<GameClock>,
+) {
+    egui::Window::new("Schedules")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for slot in ScheduleSlot::iter() {
+                    ui.colored_label(slot.color(), slot.as_ref());
+                }
+            });
+
+            egui::Grid::new("schedule_grid")
+                .spacing(egui::vec2(1.0, 2.0))
+                .show(ui, |ui| {
+                    ui.label("");
+                    for hour in 0..24 {
+                        let text = egui::RichText::new(format!("{:02}", hour));
+                        // highlight the current hour
+                        if hour == clock.hour {
+                            ui.label(text.strong().underline());
+                        } else {
+                            ui.label(text);
+                        }
+                    }
+                    ui.end_row();
+
+                    for (name, mut schedule) in query.iter_mut() {
+                        ui.label(&name.0);
+                        for hour in 0..24 {
+                            let slot = schedule.hours[hour];
+                            let button = egui::Button::new(&slot.as_ref()[..1])
+                                .fill(slot.color())
+                                .min_size(egui::vec2(18.0, 18.0));
+                            if ui.add(button).on_hover_text(slot.as_ref()).clicked() {
+                                schedule.cycle(hour);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+pub struct SchedulePlugin;
+
+impl Plugin for SchedulePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, schedule_ui.run_if(in_state(InGame)));
+    }
+}
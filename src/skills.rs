@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::Rng;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter};
+
+pub const MAX_SKILL_LEVEL: u32 = 20;
+// starting levels are rolled up to this, plus a bonus in one random skill
+const MAX_STARTING_LEVEL: u32 = 6;
+const TALENT_BONUS: u32 = 4;
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, AsRefStr)]
+pub enum SkillType {
+    Farming,
+    Construction,
+    Mining,
+    Cooking,
+    Social,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Skill {
+    pub level: u32,
+    pub xp: f32,
+}
+
+impl Skill {
+    pub fn xp_to_next_level(&self) -> f32 {
+        100.0 * (self.level + 1) as f32
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Skills {
+    pub skills: HashMap<SkillType, Skill>,
+}
+
+impl Skills {
+    pub fn roll<R: Rng>(rng: &mut R) -> Self {
+        let mut skills: HashMap<SkillType, Skill> = SkillType::iter()
+            .map(|s| (s, Skill { level: rng.gen_range(0..=MAX_STARTING_LEVEL), xp: 0.0 }))
+            .collect();
+
+        let talent = SkillType::iter().nth(rng.gen_range(0..SkillType::iter().count())).unwrap();
+        skills.entry(talent).and_modify(|s| s.level += TALENT_BONUS);
+
+        Skills { skills }
+    }
+
+    pub fn level(&self, skill: SkillType) -> u32 {
+        self.skills.get(&skill).map(|s| s.level).unwrap_or(0)
+    }
+
+    // returns true if the colonist gained a level
+    pub fn learn(&mut self, skill: SkillType, xp: f32) -> bool {
+        let skill = self.skills.entry(skill).or_default();
+        if skill.level >= MAX_SKILL_LEVEL {
+            return false;
+        }
+
+        skill.xp += xp;
+        let mut leveled = false;
+        while skill.level < MAX_SKILL_LEVEL && skill.xp >= skill.xp_to_next_level() {
+            skill.xp -= skill.xp_to_next_level();
+            skill.level += 1;
+            leveled = true;
+        }
+        leveled
+    }
+
+    // 0.6x for a complete novice, up to 2.2x at the max level
+    pub fn work_speed(&self, skill: SkillType) -> f32 {
+        0.6 + 0.08 * self.level(skill) as f32
+    }
+
+    // 0.0 - 1.0, how good the result of the work is. Skilled colonists are also more consistent
+    pub fn quality<R: Rng>(&self, skill: SkillType, rng: &mut R) -> f32 {
+        let base = self.level(skill) as f32 / MAX_SKILL_LEVEL as f32;
+        let spread = 0.3 * (1.0 - base);
+        (base + rng.gen_range(-spread..=spread)).clamp(0.0, 1.0)
+    }
+
+    // highest skills first
+    pub fn best(&self, count: usize) -> Vec<(SkillType, u32)> {
+        let mut skills: Vec<(SkillType, u32)> = SkillType::iter().map(|s| (s, self.level(s))).collect();
+        skills.sort_by(|a, b| b.1.cmp(&a.1));
+        skills.truncate(count);
+        skills
+    }
+}
@@ -0,0 +1,394 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_debug_text_overlay::screen_print;
+use bevy_egui::{egui, EguiContexts};
+use bevy_enum_filter::prelude::*;
+use rand::{thread_rng, Rng};
+use strum_macros::AsRefStr;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::health_plugin::ColonistDied;
+use crate::mood_plugin::{AddThought, MentalBreak, ThoughtKind, WITNESS_RADIUS};
+use crate::name_plugin::Name;
+use crate::pathing::Pos;
+use crate::skills::{SkillType, Skills};
+use crate::task_scorer::Busy;
+use crate::tasks::*;
+use crate::traits_plugin::{TraitRegistry, Traits};
+use crate::wander_plugin::{stop_moving, NeedsPath, Path, PathFailed, PathPending};
+use crate::world_gen_plugin::SPRITE_SIZE;
+
+const FRIEND_OPINION: f32 = 30.0;
+const RIVAL_OPINION: f32 = -30.0;
+// both colonists need at least this much opinion of each other to become partners
+const PARTNER_OPINION: f32 = 70.0;
+// partners whose opinion drops below this break up
+const BREAKUP_OPINION: f32 = 20.0;
+
+const CHAT_DISTANCE: f32 = 3.0 * SPRITE_SIZE as f32;
+const CHAT_TIME: f32 = 5.0;
+// how long a colonist chases someone around before giving up on talking to them
+const CHAT_PATIENCE: f32 = 20.0;
+const CHAT_SOCIAL_GAIN: f32 = 60.0;
+// the colonist who was talked to gets less out of it than the one who started the chat
+const LISTENER_SOCIAL_GAIN: f32 = 25.0;
+const CHAT_XP: f32 = 20.0;
+const ARGUMENT_CHANCE: f64 = 0.1;
+const CLASHING_ARGUMENT_CHANCE: f64 = 0.3;
+// opinion gained by sharing a traumatic event
+const SHARED_TRAUMA_OPINION: f32 = 5.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+pub enum RelationKind {
+    Acquaintance,
+    Friend,
+    Rival,
+    Partner,
+}
+
+#[derive(Component, Default)]
+pub struct Relationships {
+    pub opinions: HashMap<Entity, f32>,
+    pub partner: Option<Entity>,
+}
+
+impl Relationships {
+    pub fn opinion(&self, other: Entity) -> f32 {
+        *self.opinions.get(&other).unwrap_or(&0.0)
+    }
+
+    pub fn change_opinion(&mut self, other: Entity, amount: f32) {
+        let opinion = self.opinions.entry(other).or_insert(0.0);
+        *opinion = (*opinion + amount).clamp(-100.0, 100.0);
+    }
+
+    pub fn kind(&self, other: Entity) -> RelationKind {
+        match self.opinion(other) {
+            _ if self.partner == Some(other) => RelationKind::Partner,
+            o if o >= FRIEND_OPINION => RelationKind::Friend,
+            o if o <= RIVAL_OPINION => RelationKind::Rival,
+            _ => RelationKind::Acquaintance,
+        }
+    }
+}
+
+// the colonist this one is walking over to talk to
+#[derive(Component)]
+pub struct ChatPartner {
+    pub entity: Entity,
+    pub patience: f32,
+}
+
+#[derive(Component)]
+pub struct Chatting {
+    pub remaining: f32,
+}
+
+// on an idle colonist someone is walking over to, the one they've stopped to wait for and listen to
+#[derive(Component)]
+pub struct Listening {
+    pub to: Entity,
+}
+
+// only colonists with nothing better to do come over to meet whoever wants to talk
+fn is_idle(task: &AllTasks, busy: bool) -> bool {
+    *task == AllTasks::Wander && !busy
+}
+
+fn choose_chat_partner(
+    mut commands: Commands,
+    query: Query<
+        (Entity, &Relationships, &Transform),
+        (With<Enum!(AllTasks::Socialize)>, Without<ChatPartner>, Without<Chatting>),
+    >,
+    others: Query<(Entity, &Transform, &AllTasks, Has<Busy>), (With<Character>, Without<MentalBreak>)>,
+) {
+    let mut rng = thread_rng();
+
+    for (entity, relationships, transform) in query.iter() {
+        // prefer people they like, and people close by
+        let partner = others
+            .iter()
+            .filter(|(other, _, _, _)| *other != entity)
+            .map(|(other, other_transform, task, busy)| {
+                let distance = transform.translation.distance(other_transform.translation) / SPRITE_SIZE as f32;
+                let score = relationships.opinion(other) - distance + rng.gen_range(0.0..20.0);
+                (other, other_transform, is_idle(task, busy), score)
+            })
+            .max_by(|a, b| a.3.partial_cmp(&b.3).unwrap());
+
+        match partner {
+            Some((partner, partner_transform, idle, _)) => {
+                commands.entity(entity).insert((
+                    ChatPartner { entity: partner, patience: CHAT_PATIENCE },
+                    NeedsPath { pos: Pos::from_translation(partner_transform.translation) },
+                ));
+                // someone with nothing better to do stays put and waits to be talked to
+                if idle {
+                    stop_moving(commands.entity(partner).insert(Listening { to: entity }));
+                }
+            }
+            // nobody to talk to
+            None => {
+                commands.entity(entity).remove::<Busy>();
+            }
+        }
+    }
+}
+
+fn approach_chat_partner(
+    mut commands: Commands,
+    query: Query<
+        (Entity, &Transform, &ChatPartner, Option<&PathFailed>),
+        (
+            With<Enum!(AllTasks::Socialize)>,
+            Without<Chatting>,
+            Without<Path>,
+            Without<NeedsPath>,
+            Without<PathPending>,
+        ),
+    >,
+    others: Query<(&Transform, &AllTasks, Has<Busy>), With<Character>>,
+) {
+    for (entity, transform, partner, path_failed) in query.iter() {
+        let Ok((partner_transform, task, busy)) = others.get(partner.entity) else {
+            // they died or went away, pick someone else
+            commands.entity(entity).remove::<ChatPartner>();
+            continue;
+        };
+
+        if path_failed.is_some() {
+            commands.entity(entity).remove::<(ChatPartner, PathFailed, Busy)>();
+            continue;
+        }
+
+        // they may have walked off while we were on the way
+        if transform.translation.distance(partner_transform.translation) <= CHAT_DISTANCE {
+            commands.entity(entity).insert(Chatting { remaining: CHAT_TIME });
+            if is_idle(task, busy) {
+                stop_moving(commands.entity(partner.entity).insert(Listening { to: entity }));
+            }
+        } else {
+            commands.entity(entity).insert(NeedsPath { pos: Pos::from_translation(partner_transform.translation) });
+        }
+    }
+}
+
+fn chat(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<
+        (Entity, &mut Chatting, &ChatPartner, &mut Social, &mut Skills, &Traits, Option<&Name>),
+        With<Enum!(AllTasks::Socialize)>,
+    >,
+    mut relationships: Query<&mut Relationships>,
+    mut listeners: Query<&mut Social, Without<Chatting>>,
+    partners: Query<&Traits>,
+    mut thoughts: EventWriter<AddThought>,
+    trait_registry: Res<TraitRegistry>,
+) {
+    let mut rng = thread_rng();
+
+    for (entity, mut chatting, partner, mut social, mut skills, traits, name) in query.iter_mut() {
+        chatting.remaining -= time.delta_seconds();
+        if chatting.remaining > 0.0 {
+            continue;
+        }
+        commands.entity(entity).remove::<(Chatting, ChatPartner, Busy)>();
+
+        // traits that rule each other out make for awkward conversations, shared ones for good ones
+        let partner_traits = partners.get(partner.entity).ok();
+        let shared = partner_traits.map_or(0.0, |p| traits.ids.iter().filter(|id| p.ids.contains(id)).count() as f32);
+        let clashing = partner_traits.is_some_and(|p| {
+            trait_registry.defs(traits).any(|d| p.ids.iter().any(|id| d.incompatible.contains(id)))
+        });
+
+        let argument_chance = if clashing { CLASHING_ARGUMENT_CHANCE } else { ARGUMENT_CHANCE };
+        let (opinion, kind) = if rng.gen_bool(argument_chance) {
+            (-rng.gen_range(5.0..12.0), ThoughtKind::HadArgument)
+        } else {
+            let social_bonus = skills.level(SkillType::Social) as f32 * 0.3;
+            (rng.gen_range(2.0..6.0) + shared * 2.0 + social_bonus, ThoughtKind::HadChat)
+        };
+
+        social.value = (social.value + CHAT_SOCIAL_GAIN).min(100.0);
+
+        if skills.learn(SkillType::Social, CHAT_XP) {
+            if let Some(name) = name {
+                screen_print!(push, sec: 3.0, "{} got better at Social", &name.0);
+            }
+        }
+
+        if let Ok(mut own) = relationships.get_mut(entity) {
+            own.change_opinion(partner.entity, opinion);
+        }
+        thoughts.send(AddThought { entity, kind, strength: 1.0 });
+
+        // a partner who was talking to someone themselves gets their share when their own chat ends
+        let Ok(mut partner_social) = listeners.get_mut(partner.entity) else {
+            continue;
+        };
+        partner_social.value = (partner_social.value + LISTENER_SOCIAL_GAIN).min(100.0);
+        if let Ok(mut theirs) = relationships.get_mut(partner.entity) {
+            theirs.change_opinion(entity, opinion);
+        }
+        thoughts.send(AddThought { entity: partner.entity, kind, strength: 1.0 });
+    }
+}
+
+// the chat they stopped for is over, or the one who wanted it gave up
+fn stop_listening(
+    mut commands: Commands,
+    query: Query<(Entity, &Listening)>,
+    speakers: Query<&ChatPartner>,
+) {
+    for (entity, listening) in query.iter() {
+        if !speakers.get(listening.to).is_ok_and(|partner| partner.entity == entity) {
+            commands.entity(entity).remove::<Listening>();
+        }
+    }
+}
+
+fn lose_patience(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut ChatPartner), Without<Chatting>>,
+) {
+    for (entity, mut partner) in query.iter_mut() {
+        partner.patience -= time.delta_seconds();
+        if partner.patience <= 0.0 {
+            stop_moving(commands.entity(entity).remove::<(ChatPartner, Busy)>());
+        }
+    }
+}
+
+// the chat was interrupted by something more important
+fn abandon_chats(
+    mut commands: Commands,
+    query: Query<Entity, (Or<(With<ChatPartner>, With<Chatting>)>, Without<Enum!(AllTasks::Socialize)>)>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).remove::<(ChatPartner, Chatting)>();
+    }
+}
+
+fn update_partners(mut query: Query<(Entity, &mut Relationships, Option<&Name>)>) {
+    let mut breakups = vec![];
+    let mut candidates = vec![];
+
+    for (entity, relationships, _) in query.iter() {
+        match relationships.partner {
+            Some(partner) if relationships.opinion(partner) < BREAKUP_OPINION => breakups.push((entity, partner)),
+            Some(_) => {}
+            None => {
+                for (other, opinion) in relationships.opinions.iter() {
+                    if *opinion >= PARTNER_OPINION && entity < *other {
+                        candidates.push((entity, *other));
+                    }
+                }
+            }
+        }
+    }
+
+    for (a, b) in breakups {
+        for entity in [a, b] {
+            if let Ok((_, mut relationships, _)) = query.get_mut(entity) {
+                relationships.partner = None;
+            }
+        }
+    }
+
+    for (a, b) in candidates {
+        let Ok([(_, mut rel_a, name_a), (_, mut rel_b, name_b)]) = query.get_many_mut([a, b]) else {
+            continue;
+        };
+        if rel_a.partner.is_some() || rel_b.partner.is_some() || rel_b.opinion(a) < PARTNER_OPINION {
+            continue;
+        }
+
+        rel_a.partner = Some(b);
+        rel_b.partner = Some(a);
+        if let (Some(name_a), Some(name_b)) = (name_a, name_b) {
+            screen_print!(push, sec: 5.0, "{} and {} are now partners", &name_a.0, &name_b.0);
+        }
+    }
+}
+
+fn social_deaths(
+    mut died: EventReader<ColonistDied>,
+    mut thoughts: EventWriter<AddThought>,
+    mut query: Query<(Entity, &mut Relationships, &Transform)>,
+) {
+    for death in died.read() {
+        let mut witnesses = vec![];
+
+        for (entity, mut relationships, transform) in query.iter_mut() {
+            if matches!(relationships.kind(death.entity), RelationKind::Friend | RelationKind::Partner) {
+                thoughts.send(AddThought { entity, kind: ThoughtKind::LostFriend, strength: 1.0 });
+            }
+
+            relationships.opinions.remove(&death.entity);
+            if relationships.partner == Some(death.entity) {
+                relationships.partner = None;
+            }
+
+            if transform.translation.truncate().distance(death.translation.truncate()) <= WITNESS_RADIUS {
+                witnesses.push(entity);
+            }
+        }
+
+        // going through something awful together brings people closer
+        for (entity, mut relationships, _) in query.iter_mut() {
+            if !witnesses.contains(&entity) {
+                continue;
+            }
+            for other in witnesses.iter().filter(|w| **w != entity) {
+                relationships.change_opinion(*other, SHARED_TRAUMA_OPINION);
+            }
+        }
+    }
+}
+
+fn relationships_ui(
+    mut contexts: EguiContexts,
+    query: Query<(&Name, &Relationships), With<Character>>,
+    names: Query<&Name>,
+) {
+    egui::Window::new("Relationships")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for (name, relationships) in query.iter() {
+                ui.collapsing(&name.0, |ui| {
+                    for (other, opinion) in relationships.opinions.iter() {
+                        let Ok(other_name) = names.get(*other) else {
+                            continue;
+                        };
+                        ui.label(format!(
+                            "{}: {:+.0} ({})",
+                            other_name.0,
+                            opinion,
+                            relationships.kind(*other).as_ref()
+                        ));
+                    }
+                });
+            }
+        });
+}
+
+pub struct SocialPlugin;
+
+impl Plugin for SocialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, choose_chat_partner.run_if(in_state(InGame)))
+            .add_systems(Update, approach_chat_partner.run_if(in_state(InGame)))
+            .add_systems(Update, lose_patience.run_if(in_state(InGame)))
+            .add_systems(Update, chat.run_if(in_state(InGame)))
+            .add_systems(Update, stop_listening.run_if(in_state(InGame)))
+            .add_systems(Update, abandon_chats.run_if(in_state(InGame)))
+            .add_systems(Update, update_partners.run_if(in_state(InGame)))
+            .add_systems(Update, social_deaths.run_if(in_state(InGame)))
+            .add_systems(Update, relationships_ui.run_if(in_state(InGame)));
+    }
+}
@@ -0,0 +1,91 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+
+use crate::AppState::InGame;
+use crate::clock_plugin::MINUTES_PER_SECOND;
+use crate::item_plugin::{ConsumeItem, Item, ItemRegistry, SpawnItem};
+use crate::pathing::Pos;
+use crate::temperature_plugin::Temperature;
+use crate::world_gen_plugin::Indoors;
+
+const MINUTES_PER_DAY: f32 = 24.0 * 60.0;
+
+// what food turns into once it's gone off. Rot spoils too, and then it's gone for good
+const ROT_ITEM: &str = "rot";
+
+// nothing rots at or below freezing, and slower in the cold
+const FREEZING: f32 = 0.0;
+const COLD: f32 = 8.0;
+const COLD_RATE: f32 = 0.4;
+// out of the sun and the rain
+const INDOOR_RATE: f32 = 0.6;
+
+// the color a stack is tinted just before it spoils. Fresh stacks aren't tinted at all
+const ROTTEN_TINT: [f32; 3] = [0.6, 0.65, 0.35];
+
+// how fast things rot on a tile, relative to lying around outside on a mild day
+pub fn spoil_rate(temperature: f32, indoors: bool) -> f32 {
+    let cold = if temperature <= FREEZING {
+        0.0
+    } else if temperature < COLD {
+        COLD_RATE
+    } else {
+        1.0
+    };
+    if indoors {
+        cold * INDOOR_RATE
+    } else {
+        cold
+    }
+}
+
+// ages every stack that can go off. Carried ones age wherever the carrier is
+fn spoil_items(
+    time: Res<Time>,
+    registry: Res<ItemRegistry>,
+    temperature: Res<Temperature>,
+    indoors: Res<Indoors>,
+    mut query: Query<(Entity, &mut Item, &Transform, &mut Sprite)>,
+    mut consume: EventWriter<ConsumeItem>,
+    mut spawns: EventWriter<SpawnItem>,
+) {
+    let days = time.delta_seconds() * MINUTES_PER_SECOND / MINUTES_PER_DAY;
+
+    for (entity, mut item, transform, mut sprite) in query.iter_mut() {
+        // never spoils, or is already on its way out
+        let Some(spoil_days) = registry.get(&item.def).and_then(|def| def.spoil_days).filter(|_| item.rot < 1.0) else {
+            continue;
+        };
+        let pos = Pos::from_translation(transform.translation);
+        let rate = spoil_rate(temperature.at(pos), indoors.contains(pos));
+        if rate == 0.0 {
+            continue;
+        }
+
+        // ticks up every frame, and nothing that watches for changed stacks cares how rotten they are
+        let rot = (item.rot + days / spoil_days * rate).min(1.0);
+        item.bypass_change_detection().rot = rot;
+        let [r, g, b] = ROTTEN_TINT.map(|tint| 1.0 - (1.0 - tint) * item.rot);
+        sprite.color = Color::rgb(r, g, b);
+        if item.rot < 1.0 {
+            continue;
+        }
+
+        consume.send(ConsumeItem { item: entity, count: item.count });
+        if item.def != ROT_ITEM {
+            spawns.send(SpawnItem {
+                def: ROT_ITEM.to_string(),
+                count: item.count,
+                pos,
+            });
+        }
+    }
+}
+
+pub struct SpoilagePlugin;
+
+impl Plugin for SpoilagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spoil_items.run_if(in_state(InGame)));
+    }
+}
@@ -0,0 +1,339 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_egui::{egui, EguiContexts};
+use bevy_enum_filter::prelude::*;
+use strum::IntoEnumIterator;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::input_plugin::{zone_sprite, ClearZone, DesignateZone, TileRect, Tool};
+use crate::item_plugin::{DropItem, Inventory, Item, ItemCategory, ItemDef, ItemRegistry, PickUpItem};
+use crate::pathing::Pos;
+use crate::reservation_plugin::{renew_claims, Claims, Reservations, Target};
+use crate::task_scorer::Busy;
+use crate::tasks::*;
+use crate::wander_plugin::{NeedsPath, Path, PathFailed, PathPending};
+use crate::work_plugin::{AvailableWork, WorkType, MAX_PRIORITY};
+use crate::world_gen_plugin::SPRITE_SIZE;
+
+// 1 is the most important, MAX_PRIORITY the least, like work priorities
+const DEFAULT_PRIORITY: u8 = 3;
+// how close the hauler has to be to pick something up or put it down
+const HAUL_DISTANCE: f32 = 1.5 * SPRITE_SIZE as f32;
+
+#[derive(Component)]
+pub struct Stockpile {
+    pub rect: TileRect,
+    pub allowed: HashSet<ItemCategory>,
+    pub priority: u8,
+}
+
+impl Stockpile {
+    pub fn accepts(&self, category: ItemCategory) -> bool {
+        self.allowed.contains(&category)
+    }
+
+    // cycles 1 -> 2 -> ... -> MAX_PRIORITY -> 1
+    fn cycle_priority(&mut self) {
+        self.priority = self.priority % MAX_PRIORITY + 1;
+    }
+}
+
+// the item a hauler is taking somewhere, and the tile it's going to
+#[derive(Component)]
+pub struct HaulJob {
+    pub item: Entity,
+    pub dest: Pos,
+}
+
+impl Claims for HaulJob {
+    fn claimed(&self) -> Vec<Target> {
+        vec![Target::Entity(self.item), Target::Tile(self.dest)]
+    }
+}
+
+// where things can be stored right now. Built fresh whenever it's needed
+struct Storage<'a> {
+    stockpiles: Vec<&'a Stockpile>,
+    stacks: HashMap<Pos, &'a Item>,
+}
+
+impl<'a> Storage<'a> {
+    // the priority of the best stockpile `pos` is in that wants this kind of item. None if it's loose
+    fn stored_priority(&self, pos: Pos, category: ItemCategory) -> Option<u8> {
+        self.stockpiles
+            .iter()
+            .filter(|s| s.rect.contains(pos) && s.accepts(category))
+            .map(|s| s.priority)
+            .min()
+    }
+
+    // the best tile to haul an item lying at `from` to, if there's anywhere better than where it is.
+    // Higher priority stockpiles first, then the closest tile
+    // Tiles other haulers are already bringing something to are skipped
+    fn destination(&self, item: &Item, def: &ItemDef, from: Pos, reservations: &Reservations) -> Option<Pos> {
+        let current = self.stored_priority(from, def.category);
+
+        self.stockpiles
+            .iter()
+            .filter(|s| s.accepts(def.category) && current.map_or(true, |p| s.priority < p))
+            .flat_map(|s| s.rect.tiles().map(move |pos| (s.priority, pos)))
+            .filter(|(_, pos)| reservations.is_free(Target::Tile(*pos)))
+            .filter(|(_, pos)| match self.stacks.get(pos) {
+                None => true,
+                // topping up a stack of the same thing, as long as it all fits
+                Some(stack) => stack.def == item.def && stack.count + item.count <= def.stack_size,
+            })
+            .min_by_key(|(priority, pos)| (*priority, pos.distance(&from)))
+            .map(|(_, pos)| pos)
+    }
+}
+
+fn designate_stockpiles(
+    mut commands: Commands,
+    mut designated: EventReader<DesignateZone>,
+    mut cleared: EventReader<ClearZone>,
+    stockpiles: Query<(Entity, &Stockpile)>,
+) {
+    for event in designated.read() {
+        if event.tool != Tool::Stockpile {
+            continue;
+        }
+        commands.spawn((
+            zone_sprite(event.rect, Color::rgba(0.9, 0.7, 0.2, 0.2)),
+            Stockpile {
+                rect: event.rect,
+                // nobody wants rot in with the food unless they ask for it
+                allowed: ItemCategory::iter().filter(|c| *c != ItemCategory::Waste).collect(),
+                priority: DEFAULT_PRIORITY,
+            },
+        ));
+    }
+
+    for event in cleared.read() {
+        if event.tool != Tool::Stockpile {
+            continue;
+        }
+        for (entity, stockpile) in stockpiles.iter() {
+            if stockpile.rect.contains(event.pos) {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+// where a loose item should go, if nobody is eating or hauling it yet and it has somewhere better to be
+fn haul_destination(
+    entity: Entity,
+    item: &Item,
+    pos: Pos,
+    registry: &ItemRegistry,
+    storage: &Storage,
+    reservations: &Reservations,
+) -> Option<Pos> {
+    if !reservations.is_free(Target::Entity(entity)) {
+        return None;
+    }
+    let def = registry.get(&item.def)?;
+    storage.destination(item, def, pos, reservations)
+}
+
+// every item that could be hauled right now, with where it is and where it's going
+fn haulable(
+    items: &Query<(Entity, &Item, &Pos)>,
+    registry: &ItemRegistry,
+    storage: &Storage,
+    reservations: &Reservations,
+) -> Vec<(Entity, Pos, Pos)> {
+    items
+        .iter()
+        .filter_map(|(entity, item, pos)| {
+            let dest = haul_destination(entity, item, *pos, registry, storage, reservations)?;
+            Some((entity, *pos, dest))
+        })
+        .collect()
+}
+
+fn find_haul_work(
+    stockpiles: Query<&Stockpile>,
+    items: Query<(Entity, &Item, &Pos)>,
+    registry: Res<ItemRegistry>,
+    reservations: Res<Reservations>,
+    mut available_work: ResMut<AvailableWork>,
+) {
+    let storage = Storage {
+        stockpiles: stockpiles.iter().collect(),
+        stacks: items.iter().map(|(_, item, pos)| (*pos, item)).collect(),
+    };
+
+    // finding a destination looks at every stockpile tile, so stop at the first item that has one
+    let any_haulable = items
+        .iter()
+        .any(|(entity, item, pos)| haul_destination(entity, item, *pos, &registry, &storage, &reservations).is_some());
+    let tasks = if any_haulable {
+        vec![AllTasks::Haul]
+    } else {
+        vec![]
+    };
+    available_work.jobs.insert(WorkType::Hauling, tasks);
+}
+
+// haulers go for the closest item that needs moving, and claim both it and the tile it's going to
+fn take_haul_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    query: Query<(Entity, &Transform), (With<Character>, With<Enum!(AllTasks::Haul)>, With<Busy>, Without<HaulJob>)>,
+    stockpiles: Query<&Stockpile>,
+    items: Query<(Entity, &Item, &Pos)>,
+    registry: Res<ItemRegistry>,
+    mut reservations: ResMut<Reservations>,
+) {
+    let storage = Storage {
+        stockpiles: stockpiles.iter().collect(),
+        stacks: items.iter().map(|(_, item, pos)| (*pos, item)).collect(),
+    };
+
+    for (entity, transform) in query.iter() {
+        let here = Pos::from_translation(transform.translation);
+        let job = haulable(&items, &registry, &storage, &reservations)
+            .into_iter()
+            .min_by_key(|(_, pos, _)| pos.distance(&here));
+
+        match job {
+            Some((item, pos, dest)) => {
+                let now = time.elapsed_seconds();
+                reservations.reserve(Target::Entity(item), entity, AllTasks::Haul, now);
+                reservations.reserve(Target::Tile(dest), entity, AllTasks::Haul, now);
+                commands.entity(entity).insert((HaulJob { item, dest }, NeedsPath { pos }));
+            }
+            // someone else got there first
+            None => {
+                commands.entity(entity).remove::<Busy>();
+            }
+        }
+    }
+}
+
+fn within_reach(transform: &Transform, pos: Pos) -> bool {
+    let target = Vec2::new((pos.0 * SPRITE_SIZE) as f32, (pos.1 * SPRITE_SIZE) as f32);
+    transform.translation.truncate().distance(target) <= HAUL_DISTANCE
+}
+
+// haulers carry as much of the stack as they can, and come back for the rest later
+fn do_haul_jobs(
+    mut commands: Commands,
+    time: Res<Time>,
+    query: Query<
+        (Entity, &Transform, &HaulJob, &Inventory, Option<&PathFailed>),
+        (With<Enum!(AllTasks::Haul)>, Without<Path>, Without<NeedsPath>, Without<PathPending>),
+    >,
+    items: Query<(&Item, &Pos)>,
+    mut pick_ups: EventWriter<PickUpItem>,
+    mut drops: EventWriter<DropItem>,
+    mut reservations: ResMut<Reservations>,
+) {
+    for (entity, transform, job, inventory, path_failed) in query.iter() {
+        let here = Pos::from_translation(transform.translation);
+
+        if path_failed.is_some() {
+            // either the item or the tile it was going to
+            let target = if inventory.is_empty() { Target::Entity(job.item) } else { Target::Tile(job.dest) };
+            reservations.mark_unreachable(target, time.elapsed_seconds());
+            inventory.drop_all(here, &mut drops);
+            commands.entity(entity).remove::<(HaulJob, PathFailed, Busy)>();
+            continue;
+        }
+
+        if !inventory.is_empty() {
+            if within_reach(transform, job.dest) {
+                inventory.drop_all(job.dest, &mut drops);
+                commands.entity(entity).remove::<(HaulJob, Busy)>();
+            } else {
+                commands.entity(entity).insert(NeedsPath { pos: job.dest });
+            }
+            continue;
+        }
+
+        // eaten, or picked up by someone else
+        let Ok((item, pos)) = items.get(job.item) else {
+            commands.entity(entity).remove::<(HaulJob, Busy)>();
+            continue;
+        };
+        if within_reach(transform, *pos) {
+            pick_ups.send(PickUpItem {
+                item: job.item,
+                by: entity,
+                count: item.count,
+            });
+            commands.entity(entity).insert(NeedsPath { pos: job.dest });
+        } else {
+            commands.entity(entity).insert(NeedsPath { pos: *pos });
+        }
+    }
+}
+
+// the hauler went off to do something more important, and puts down whatever they had
+fn abandon_hauls(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &Inventory), (With<HaulJob>, Without<Enum!(AllTasks::Haul)>)>,
+    mut drops: EventWriter<DropItem>,
+) {
+    for (entity, transform, inventory) in query.iter() {
+        inventory.drop_all(Pos::from_translation(transform.translation), &mut drops);
+        commands.entity(entity).remove::<HaulJob>();
+    }
+}
+
+fn stockpiles_ui(mut contexts: EguiContexts, mut query: Query<(Entity, &mut Stockpile)>) {
+    egui::Window::new("Stockpiles")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for (entity, mut stockpile) in query.iter_mut() {
+                ui.push_id(entity, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} tiles, priority", stockpile.rect.area()));
+                        if ui.button(stockpile.priority.to_string()).clicked() {
+                            stockpile.cycle_priority();
+                        }
+                        if ui.button("Food only").clicked() {
+                            stockpile.allowed = ItemCategory::iter().filter(|c| c.is_food()).collect();
+                        }
+                        if ui.button("Materials only").clicked() {
+                            stockpile.allowed = [ItemCategory::Material].into_iter().collect();
+                        }
+                        if ui.button("Everything").clicked() {
+                            stockpile.allowed = ItemCategory::iter().collect();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        for category in ItemCategory::iter() {
+                            let mut allowed = stockpile.accepts(category);
+                            if ui.checkbox(&mut allowed, category.as_ref()).changed() {
+                                if allowed {
+                                    stockpile.allowed.insert(category);
+                                } else {
+                                    stockpile.allowed.remove(&category);
+                                }
+                            }
+                        }
+                    });
+                });
+                ui.separator();
+            }
+        });
+}
+
+pub struct StockpilePlugin;
+
+impl Plugin for StockpilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, designate_stockpiles.run_if(in_state(InGame)))
+            .add_systems(Update, find_haul_work.after(designate_stockpiles).run_if(in_state(InGame)))
+            .add_systems(Update, take_haul_jobs.after(find_haul_work).run_if(in_state(InGame)))
+            .add_systems(Update, do_haul_jobs.after(take_haul_jobs).run_if(in_state(InGame)))
+            .add_systems(Update, renew_claims::<HaulJob>.run_if(in_state(InGame)))
+            .add_systems(Update, abandon_hauls.run_if(in_state(InGame)))
+            .add_systems(Update, stockpiles_ui.run_if(in_state(InGame)));
+    }
+}
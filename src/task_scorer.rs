@@ -5,11 +5,69 @@ use crate::AppState::InGame;
 use bevy::prelude::*;
 use bevy_debug_text_overlay::screen_print;
 use bevy_enum_filter::Enum;
+use serde::Serialize;
+use strum::IntoEnumIterator;
+
+use crate::clock_plugin::GameClock;
+use crate::decision_log_plugin::DecisionLog;
+use crate::health_plugin::Collapsed;
+use crate::mood_plugin::MentalBreak;
+use crate::schedule_plugin::{Schedule, ScheduleSlot};
+use crate::skills::Skills;
+use crate::traits_plugin::{TraitRegistry, Traits, WORK_TASK_KEY};
+use crate::wander_plugin::stop_moving;
+use crate::work_plugin::{AvailableWork, WorkPriorities, WorkType};
 
 pub trait Task {
     fn score(&self) -> f32;
 }
 
+// One input to a candidate's score, kept around so decisions can be explained later
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Consideration {
+    pub name: String,
+    pub value: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Candidate {
+    pub task: AllTasks,
+    pub score: f32,
+    pub considerations: Vec<Consideration>,
+}
+
+impl Candidate {
+    pub fn new(task: AllTasks) -> Self {
+        Candidate {
+            task,
+            score: 0.0,
+            considerations: vec![],
+        }
+    }
+
+    // adds `value` to the score and records where it came from
+    pub fn with(mut self, name: &str, value: f32) -> Self {
+        self.score += value;
+        self.considerations.push(Consideration {
+            name: name.to_string(),
+            value,
+        });
+        self
+    }
+}
+
+// during scheduled hours colonists go to bed or socialize early, as long as the need isn't full yet
+const SCHEDULED_SLEEP_BELOW: f32 = 80.0;
+const SCHEDULED_SLEEP_SCORE: f32 = 7.0;
+const SCHEDULED_SOCIAL_BELOW: f32 = 80.0;
+const SCHEDULED_SOCIAL_SCORE: f32 = 6.0;
+const SCHEDULED_WORK_SCORE: f32 = 2.0;
+// work during sleep hours only happens if there's nothing better to do
+const OFF_SHIFT_WORK_SCORE: f32 = -4.0;
+
+// below this, hunger or thirst interrupt whatever else the colonist is busy with
+const URGENT_NEED: f32 = 15.0;
+
 pub struct TaskScoringPlugin;
 
 #[derive(Component)]
@@ -17,18 +75,76 @@ pub struct Busy;
 
 fn score_basic_tasks(
     mut commands: Commands,
-    mut query: Query<(Entity, &Name, &mut AllTasks, &Thirst, &Hunger, &Sleep), Without<Busy>>,
+    time: Res<Time>,
+    mut query: Query<
+        (Entity, &Name, &mut AllTasks, &Thirst, &Hunger, &Sleep, &Social, &WorkPriorities, &Schedule, &Traits, &mut DecisionLog),
+        Without<Busy>,
+    >,
+    available_work: Res<AvailableWork>,
+    trait_registry: Res<TraitRegistry>,
+    clock: Res<GameClock>,
+    food: Res<FoodSupply>,
 ) {
-    for (entity, name, mut task, thirst, hunger, sleep) in query.iter_mut() {
-        let mut ratings = vec![(AllTasks::Wander, 1.0)];
+    for (entity, name, mut task, thirst, hunger, sleep, social, priorities, schedule, traits, mut decision_log) in query.iter_mut() {
+        let mut candidates = vec![Candidate::new(AllTasks::Wander).with("idle", 1.0)];
 
-        ratings.push((AllTasks::Eat, hunger.score()));
-        ratings.push((AllTasks::Drink, thirst.score()));
-        ratings.push((AllTasks::Sleep, sleep.score()));
+        let mut eat_candidate = Candidate::new(AllTasks::Eat).with("hunger", hunger.score());
+        if food.units == 0 {
+            eat_candidate = eat_candidate.with("no food", -hunger.score());
+        }
+        candidates.push(eat_candidate);
+        candidates.push(Candidate::new(AllTasks::Drink).with("thirst", thirst.score()));
+        let slot = schedule.slot(clock.hour);
 
-        ratings.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        let mut sleep_candidate = Candidate::new(AllTasks::Sleep).with("sleep", sleep.score());
+        if slot == ScheduleSlot::Sleep && sleep.value < SCHEDULED_SLEEP_BELOW {
+            sleep_candidate = sleep_candidate.with("schedule", SCHEDULED_SLEEP_SCORE);
+        }
+        candidates.push(sleep_candidate);
 
-        *task = ratings.iter().nth(0).unwrap().0;
+        let mut social_candidate = Candidate::new(AllTasks::Socialize).with("social", social.score());
+        if slot == ScheduleSlot::Recreation && social.value < SCHEDULED_SOCIAL_BELOW {
+            social_candidate = social_candidate.with("schedule", SCHEDULED_SOCIAL_SCORE);
+        }
+        candidates.push(social_candidate);
+
+        for work_type in WorkType::iter() {
+            let Some(score) = priorities.score(work_type) else {
+                continue;
+            };
+            if let Some(work_task) = available_work.jobs.get(&work_type).and_then(|tasks| tasks.first()) {
+                let mut candidate = Candidate::new(*work_task).with(&format!("{} priority", work_type.as_ref()), score);
+                for (trait_name, bonus) in trait_registry.task_scores(traits, WORK_TASK_KEY) {
+                    candidate = candidate.with(&trait_name, bonus);
+                }
+                match slot {
+                    ScheduleSlot::Work => candidate = candidate.with("schedule", SCHEDULED_WORK_SCORE),
+                    ScheduleSlot::Sleep => candidate = candidate.with("schedule", OFF_SHIFT_WORK_SCORE),
+                    _ => {}
+                }
+                candidates.push(candidate);
+            }
+        }
+
+        for candidate in candidates.iter_mut() {
+            // traits only sway tasks the colonist has a reason to do, a glutton still won't eat when full
+            if candidate.score <= 0.0 {
+                continue;
+            }
+            for (trait_name, bonus) in trait_registry.task_scores(traits, candidate.task.as_ref()) {
+                *candidate = candidate.clone().with(&trait_name, bonus);
+            }
+        }
+
+        candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+
+        // whatever path the colonist was following belonged to the old task
+        if *task != candidates[0].task {
+            stop_moving(&mut commands.entity(entity));
+        }
+
+        *task = candidates[0].task;
+        decision_log.record(time.elapsed_seconds(), candidates);
 
         // update "Busy" flag. Adding this causes the entity to be skipped in the scoring query
         match *task {
@@ -43,6 +159,23 @@ fn score_basic_tasks(
     }
 }
 
+// long tasks give way to urgent needs, so nobody chats themselves to death.
+// Mental breaks and collapses are not up to the colonist. Hunger only counts if there's food to go to
+fn interrupt_for_needs(
+    mut commands: Commands,
+    food: Res<FoodSupply>,
+    query: Query<(Entity, &AllTasks, &Thirst, &Hunger), (With<Busy>, Without<MentalBreak>, Without<Collapsed>)>,
+) {
+    for (entity, task, thirst, hunger) in query.iter() {
+        if matches!(task, AllTasks::Eat | AllTasks::Drink | AllTasks::Sleep) {
+            continue;
+        }
+        if thirst.value < URGENT_NEED || (hunger.value < URGENT_NEED && food.units > 0) {
+            commands.entity(entity).remove::<Busy>();
+        }
+    }
+}
+
 #[allow(unused)]
 fn begin_eat(mut commands: Commands, query: Query<Entity, Added<Enum!(AllTasks::Eat)>>) {
     for entity in &query {
@@ -64,10 +197,16 @@ fn check_task(query: Query<(Entity, &AllTasks), With<Character>>) {
 }
 
 fn render_task_text(
-    p_query: Query<(Entity, &Children, &AllTasks, &Name), With<Character>>,
+    p_query: Query<(Entity, &Children, &AllTasks, &Name, &Skills), With<Character>>,
     mut c_query: Query<&mut Text>,
 ) {
-    for (_, children, task, name) in p_query.iter() {
+    for (_, children, task, name, skills) in p_query.iter() {
+        let best_skills: Vec<String> = skills
+            .best(2)
+            .iter()
+            .map(|(skill, level)| format!("{} {}", skill.as_ref(), level))
+            .collect();
+
         // `children` is a collection of Entity IDs
         for &child in children.iter() {
             // get the text child
@@ -77,7 +216,7 @@ fn render_task_text(
                 Ok(mut t) => {
                     t.sections.clear();
                     t.sections.push(TextSection {
-                        value: format!("{}\n{}", &name.0, task.as_ref().to_string()),
+                        value: format!("{}\n{}\n{}", &name.0, task.as_ref().to_string(), best_skills.join(", ")),
                         style: Default::default(),
                     });
                 }
@@ -90,6 +229,7 @@ fn render_task_text(
 impl Plugin for TaskScoringPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, score_basic_tasks)
+            .add_systems(Update, interrupt_for_needs.run_if(in_state(InGame)))
             //.add_systems(Update, begin_eat.run_if(in_state(InGame)))
             .add_systems(Update, render_task_text.run_if(in_state(InGame)))
             .add_systems(Update, check_task);
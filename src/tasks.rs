@@ -1,19 +1,49 @@
+use crate::bed_plugin::{Bed, SleepSpot, GROUND_SLEEP_QUALITY};
 use crate::character_plugin::Character;
+use crate::health_plugin::Collapsed;
+use crate::item_plugin::{ConsumeItem, Item, ItemCategory, ItemRegistry};
+use crate::mood_plugin::{AddThought, ThoughtKind};
+use crate::name_plugin::Name;
+use crate::pathing::Pos;
+use crate::reservation_plugin::{renew_claims, Claims, Reservations, Target};
 use crate::task_scorer::{Busy, Task};
+use crate::wander_plugin::{NeedsPath, Path, PathFailed, PathPending};
+use crate::weather_plugin::Weather;
+use crate::world_gen_plugin::SPRITE_SIZE;
 use crate::AppState;
 use crate::AppState::InGame;
 use bevy::app::App;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_debug_text_overlay::screen_print;
 use bevy_enum_filter::{Enum, EnumFilter};
 use rand::{thread_rng, Rng};
+use serde::Serialize;
 use strum_macros::{AsRefStr, EnumString, IntoStaticStr};
 
-#[derive(EnumFilter, Component, Debug, Clone, Copy, EnumString, IntoStaticStr, AsRefStr)]
+// seconds it takes to eat one item
+const EAT_TIME: f32 = 2.0;
+// how close the colonist has to be to eat something
+const EAT_DISTANCE: f32 = 1.5 * SPRITE_SIZE as f32;
+// how much a tile of walking takes off a food's appeal
+const FOOD_DISTANCE_PENALTY: f32 = 0.5;
+// seconds between warnings that the colony has nothing to eat
+const NO_FOOD_WARNING_INTERVAL: f32 = 30.0;
+
+#[derive(EnumFilter, Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, EnumString, IntoStaticStr, AsRefStr)]
 pub enum AllTasks {
     Wander,
     Drink,
     Eat,
     Sleep,
+    Socialize,
+    Till,
+    Sow,
+    Tend,
+    Harvest,
+    Haul,
+    Build,
+    Craft,
 }
 
 impl Default for AllTasks {
@@ -40,6 +70,12 @@ pub struct Sleep {
     pub drain_rate: f32,
 }
 
+#[derive(Component)]
+pub struct Social {
+    pub value: f32,
+    pub drain_rate: f32,
+}
+
 impl Default for Thirst {
     fn default() -> Self {
         Thirst {
@@ -53,7 +89,9 @@ impl Default for Hunger {
     fn default() -> Self {
         Hunger {
             value: 100.0,
-            drain_rate: 2.0,
+            // every bite is a real food item now, so hunger has to drain on the colony's time scale. A
+            // full stomach lasts a bit over a day, so a colonist eats roughly once a day
+            drain_rate: 0.5,
         }
     }
 }
@@ -67,6 +105,15 @@ impl Default for Sleep {
     }
 }
 
+impl Default for Social {
+    fn default() -> Self {
+        Social {
+            value: 100.0,
+            drain_rate: 1.5,
+        }
+    }
+}
+
 impl Task for Thirst {
     fn score(&self) -> f32 {
         if self.value < 50.0 {
@@ -97,35 +144,201 @@ impl Task for Sleep {
     }
 }
 
+impl Task for Social {
+    fn score(&self) -> f32 {
+        if self.value < 30.0 {
+            8.0
+        } else {
+            0.0
+        }
+    }
+}
+
+// food units nobody has claimed yet, or failed to get to lately. Without any, colonists can't eat
+#[derive(Resource, Default)]
+pub struct FoodSupply {
+    pub units: u32,
+}
+
+// the food stack a hungry colonist is going for, and how far into the current bite they are
+#[derive(Component)]
+pub struct EatTarget {
+    pub item: Entity,
+    pub progress: f32,
+}
+
+impl Claims for EatTarget {
+    fn claimed(&self) -> Vec<Target> {
+        vec![Target::Entity(self.item)]
+    }
+}
+
+// how much colonists like eating this kind of thing, on top of how filling it is
+fn food_preference(category: ItemCategory) -> f32 {
+    match category {
+        ItemCategory::Meal => 15.0,
+        ItemCategory::RawFood => 0.0,
+        ItemCategory::Drink => -10.0,
+        ItemCategory::Material | ItemCategory::Waste => 0.0,
+    }
+}
+
+fn count_food(
+    mut supply: ResMut<FoodSupply>,
+    registry: Res<ItemRegistry>,
+    items: Query<(Entity, &Item)>,
+    reservations: Res<Reservations>,
+) {
+    supply.units = items
+        .iter()
+        .filter(|(_, item)| registry.get(&item.def).is_some_and(|def| def.nutrition > 0.0))
+        .filter(|(entity, _)| reservations.is_reachable(Target::Entity(*entity)))
+        .map(|(entity, item)| item.count.saturating_sub(reservations.claimants(Target::Entity(entity)) as u32))
+        .sum();
+}
+
+// hungry colonists pick the best food around, by how filling and tasty it is and how far away
+// several colonists can share a stack, one per item in it
+fn choose_food(
+    mut commands: Commands,
+    time: Res<Time>,
+    registry: Res<ItemRegistry>,
+    query: Query<(Entity, &Transform), (With<Character>, With<Enum!(AllTasks::Eat)>, Without<EatTarget>)>,
+    items: Query<(Entity, &Item, &Pos)>,
+    mut reservations: ResMut<Reservations>,
+) {
+    for (entity, transform) in query.iter() {
+        let here = Pos::from_translation(transform.translation);
+        let best = items
+            .iter()
+            .filter(|(item_entity, item, _)| item.count as usize > reservations.claimants(Target::Entity(*item_entity)))
+            .filter(|(item_entity, _, _)| reservations.is_reachable(Target::Entity(*item_entity)))
+            .filter_map(|(item_entity, item, pos)| {
+                let def = registry.get(&item.def).filter(|def| def.nutrition > 0.0)?;
+                let score = def.nutrition + food_preference(def.category) - pos.distance(&here) as f32 * FOOD_DISTANCE_PENALTY;
+                Some((item_entity, item.count, *pos, score))
+            })
+            .max_by(|a, b| a.3.partial_cmp(&b.3).unwrap());
+
+        match best {
+            Some((item, count, pos, _)) => {
+                reservations.share(Target::Entity(item), entity, AllTasks::Eat, time.elapsed_seconds(), count as usize);
+                commands.entity(entity).insert((EatTarget { item, progress: 0.0 }, NeedsPath { pos }));
+            }
+            // the scorer only offers Eat while there's food, but someone may have just taken the last of it
+            None => {
+                commands.entity(entity).remove::<Busy>();
+            }
+        }
+    }
+}
+
+fn warn_no_food(
+    time: Res<Time>,
+    supply: Res<FoodSupply>,
+    mut since_warning: Local<f32>,
+    query: Query<(&Hunger, &Name), With<Character>>,
+) {
+    *since_warning += time.delta_seconds();
+    if supply.units > 0 || *since_warning < NO_FOOD_WARNING_INTERVAL {
+        return;
+    }
+    if let Some((_, name)) = query.iter().find(|(hunger, _)| hunger.score() > 0.0) {
+        screen_print!(push, sec: 5.0, "The colony has no food! {} is getting hungry", &name.0);
+        *since_warning = 0.0;
+    }
+}
+
 fn hunger_system(time: Res<Time>, mut query: Query<&mut Hunger>) {
     for mut hunger in query.iter_mut() {
-        hunger.value -= hunger.drain_rate * time.delta_seconds();
+        hunger.value = (hunger.value - hunger.drain_rate * time.delta_seconds()).max(0.0);
     }
 }
 
-fn thirst_system(time: Res<Time>, mut query: Query<&mut Thirst>) {
+fn thirst_system(time: Res<Time>, weather: Res<Weather>, mut query: Query<&mut Thirst>) {
     for mut thirst in query.iter_mut() {
-        thirst.value -= thirst.drain_rate * time.delta_seconds();
+        let drain = thirst.drain_rate * weather.thirst_multiplier();
+        thirst.value = (thirst.value - drain * time.delta_seconds()).max(0.0);
     }
 }
 
 fn sleep_system(time: Res<Time>, mut query: Query<&mut Sleep>) {
     for mut sleep in query.iter_mut() {
-        sleep.value -= sleep.drain_rate * time.delta_seconds();
+        sleep.value = (sleep.value - sleep.drain_rate * time.delta_seconds()).max(0.0);
+    }
+}
+
+fn social_system(time: Res<Time>, mut query: Query<&mut Social>) {
+    for mut social in query.iter_mut() {
+        social.value = (social.value - social.drain_rate * time.delta_seconds()).max(0.0);
     }
 }
 
+// walks over to the food and eats it one item at a time, until full or the stack runs out
 fn eat(
     mut commands: Commands,
     time: Res<Time>,
-    mut query: Query<(Entity, &mut Hunger), (With<Character>, With<Enum!(AllTasks::Eat)>)>,
+    registry: Res<ItemRegistry>,
+    mut thoughts: EventWriter<AddThought>,
+    mut consume: EventWriter<ConsumeItem>,
+    mut query: Query<
+        (Entity, &Transform, &mut EatTarget, &mut Hunger, Option<&PathFailed>),
+        (With<Character>, With<Enum!(AllTasks::Eat)>, Without<Path>, Without<NeedsPath>, Without<PathPending>),
+    >,
+    items: Query<(&Item, &Pos)>,
+    mut reservations: ResMut<Reservations>,
 ) {
-    for (entity, mut hunger) in query.iter_mut() {
-        hunger.value += thread_rng().gen_range(10.0..50.0) * time.delta_seconds()
-            + hunger.drain_rate * time.delta_seconds();
-        if hunger.value >= 100.0 {
-            commands.entity(entity).remove::<Busy>();
+    // bites taken this frame, which the stacks don't show until the ConsumeItem events are handled
+    let mut bitten: HashMap<Entity, u32> = HashMap::new();
+
+    for (entity, transform, mut target, mut hunger, path_failed) in query.iter_mut() {
+        if path_failed.is_some() {
+            reservations.mark_unreachable(Target::Entity(target.item), time.elapsed_seconds());
+            commands.entity(entity).remove::<(EatTarget, PathFailed, Busy)>();
+            continue;
+        }
+        // someone else ate it first, look for something else
+        let Ok((item, pos)) = items.get(target.item) else {
+            reservations.release(Target::Entity(target.item), entity);
+            commands.entity(entity).remove::<EatTarget>();
+            continue;
+        };
+        let left = item.count.saturating_sub(*bitten.get(&target.item).unwrap_or(&0));
+        let Some(def) = registry.get(&item.def).filter(|_| left > 0) else {
+            reservations.release(Target::Entity(target.item), entity);
+            commands.entity(entity).remove::<EatTarget>();
+            continue;
+        };
+
+        let food = Vec2::new((pos.0 * SPRITE_SIZE) as f32, (pos.1 * SPRITE_SIZE) as f32);
+        if transform.translation.truncate().distance(food) > EAT_DISTANCE {
+            commands.entity(entity).insert(NeedsPath { pos: *pos });
+            continue;
+        }
+
+        target.progress += time.delta_seconds();
+        if target.progress < EAT_TIME {
+            continue;
         }
+        target.progress = 0.0;
+
+        consume.send(ConsumeItem { item: target.item, count: 1 });
+        *bitten.entry(target.item).or_insert(0) += 1;
+        hunger.value = (hunger.value + def.nutrition).min(100.0);
+
+        // stop once another bite would mostly go to waste
+        if left == 1 || hunger.value + def.nutrition / 2.0 > 100.0 {
+            commands.entity(entity).remove::<(EatTarget, Busy)>();
+            let kind = if def.category == ItemCategory::Meal { ThoughtKind::AteMeal } else { ThoughtKind::AteRawFood };
+            thoughts.send(AddThought { entity, kind, strength: 1.0 });
+        }
+    }
+}
+
+// the colonist stopped eating for something more important
+fn abandon_meals(mut commands: Commands, query: Query<Entity, (With<EatTarget>, Without<Enum!(AllTasks::Eat)>)>) {
+    for entity in query.iter() {
+        commands.entity(entity).remove::<EatTarget>();
     }
 }
 
@@ -143,16 +356,43 @@ fn drink(
     }
 }
 
+// walks to the spot picked by choose_sleep_spot and sleeps there. A good bed means a faster, happier rest
 fn sleep(
     mut commands: Commands,
     time: Res<Time>,
-    mut query: Query<(Entity, &mut Sleep), (With<Character>, With<Enum!(AllTasks::Sleep)>)>,
+    mut thoughts: EventWriter<AddThought>,
+    mut query: Query<
+        (Entity, &Transform, &mut Sleep, Option<&SleepSpot>, Has<Collapsed>),
+        (With<Character>, With<Enum!(AllTasks::Sleep)>, Without<Path>, Without<NeedsPath>, Without<PathPending>),
+    >,
+    beds: Query<&Bed>,
 ) {
-    for (entity, mut sleep) in query.iter_mut() {
-        sleep.value += thread_rng().gen_range(2.0..16.0) * time.delta_seconds()
+    for (entity, transform, mut sleep, spot, collapsed) in query.iter_mut() {
+        let bed = match spot {
+            // passed out wherever they were standing
+            _ if collapsed => None,
+            Some(SleepSpot::Bed { bed, pos }) => {
+                if Pos::from_translation(transform.translation) != *pos {
+                    commands.entity(entity).insert(NeedsPath { pos: *pos });
+                    continue;
+                }
+                beds.get(*bed).ok()
+            }
+            Some(SleepSpot::Ground) => None,
+            None => continue,
+        };
+        let quality = bed.map_or(GROUND_SLEEP_QUALITY, |bed| bed.comfort);
+
+        sleep.value += thread_rng().gen_range(2.0..16.0) * quality * time.delta_seconds()
             + sleep.drain_rate * time.delta_seconds();
         if sleep.value >= 100.0 {
-            commands.entity(entity).remove::<Busy>();
+            commands.entity(entity).remove::<(Busy, SleepSpot)>();
+            // a hard bed is still better than the ground, just not by as much
+            let (kind, strength) = match bed {
+                Some(bed) => (ThoughtKind::SleptInBed, bed.comfort),
+                None => (ThoughtKind::SleptOutside, 1.0),
+            };
+            thoughts.send(AddThought { entity, kind, strength });
         }
     }
 }
@@ -164,7 +404,14 @@ impl Plugin for BasicTasksPlugin {
         app.add_systems(Update, hunger_system.run_if(in_state(AppState::InGame)))
             .add_systems(Update, thirst_system.run_if(in_state(AppState::InGame)))
             .add_systems(Update, sleep_system.run_if(in_state(AppState::InGame)))
-            .add_systems(Update, eat.run_if(in_state(InGame)))
+            .add_systems(Update, social_system.run_if(in_state(AppState::InGame)))
+            .init_resource::<FoodSupply>()
+            .add_systems(Update, count_food.run_if(in_state(InGame)))
+            .add_systems(Update, choose_food.after(count_food).run_if(in_state(InGame)))
+            .add_systems(Update, eat.after(choose_food).run_if(in_state(InGame)))
+            .add_systems(Update, renew_claims::<EatTarget>.run_if(in_state(InGame)))
+            .add_systems(Update, abandon_meals.run_if(in_state(InGame)))
+            .add_systems(Update, warn_no_food.after(count_food).run_if(in_state(InGame)))
             .add_systems(Update, drink.run_if(in_state(InGame)))
             .add_systems(Update, sleep.run_if(in_state(InGame)));
     }
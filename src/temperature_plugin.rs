@@ -0,0 +1,220 @@
+use std::f32::consts::TAU;
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_debug_text_overlay::screen_print;
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::clock_plugin::{GameClock, Season};
+use crate::construction_plugin::{Structure, StructureRegistry};
+use crate::health_plugin::{DamageCause, Health};
+use crate::name_plugin::Name;
+use crate::pathing::Pos;
+use crate::weather_plugin::Weather;
+use crate::world_gen_plugin::Indoors;
+
+// all temperatures are in degrees celsius
+// how much warmer it is at the hottest time of day than on average, and colder at the coldest
+const DAILY_SWING: f32 = 6.0;
+const COLDEST_HOUR: f32 = 4.0;
+// what a perfectly insulated room settles at, regardless of the weather outside
+const SHELTER_TEMPERATURE: f32 = 18.0;
+// how much of the way to SHELTER_TEMPERATURE walls and a door get a room. Enough to keep a winter
+// night bearable, a fire does the rest
+const ROOM_INSULATION: f32 = 0.75;
+
+// how fast exposure builds up per second, per degree outside the comfort range
+const EXPOSURE_RATE: f32 = 0.005;
+const EXPOSURE_RECOVERY: f32 = 0.05;
+// exposure below this is just uncomfortable, above it the colonist takes damage
+const EXPOSURE_HARMFUL: f32 = 0.3;
+// health lost per second at full exposure. A little more than a healthy colonist heals, so a day out in
+// the cold wears them down but a night in a warm room puts them right
+const EXPOSURE_DAMAGE: f32 = 0.6;
+
+// how long a plant survives outside its temperature range, in seconds
+pub const PLANT_EXPOSURE_LIMIT: f32 = 60.0;
+
+#[derive(Resource)]
+pub struct Temperature {
+    pub ambient: f32,
+    // only tiles that differ from the ambient temperature
+    pub tiles: HashMap<Pos, f32>,
+}
+
+impl Default for Temperature {
+    fn default() -> Self {
+        Temperature {
+            ambient: season_temperature(Season::default()),
+            tiles: HashMap::new(),
+        }
+    }
+}
+
+impl Temperature {
+    pub fn at(&self, pos: Pos) -> f32 {
+        *self.tiles.get(&pos).unwrap_or(&self.ambient)
+    }
+}
+
+fn season_temperature(season: Season) -> f32 {
+    match season {
+        Season::Spring => 12.0,
+        Season::Summer => 24.0,
+        Season::Autumn => 10.0,
+        Season::Winter => -5.0,
+    }
+}
+
+// campfires, heaters etc. Warms (or with negative output, cools) the tiles around it
+#[derive(Component)]
+pub struct HeatSource {
+    pub output: f32,
+    // in tiles
+    pub radius: i32,
+}
+
+#[derive(Component)]
+pub struct ComfortRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for ComfortRange {
+    fn default() -> Self {
+        ComfortRange { min: 5.0, max: 30.0 }
+    }
+}
+
+// 0.0 - 1.0, how badly the cold or heat has gotten to the colonist
+#[derive(Component, Default)]
+pub struct Exposure {
+    pub hypothermia: f32,
+    pub heatstroke: f32,
+}
+
+// the range a plant can live in
+#[derive(Component)]
+pub struct Hardiness {
+    pub min: f32,
+    pub max: f32,
+    // seconds spent outside the range
+    pub exposure: f32,
+}
+
+impl Hardiness {
+    pub fn new(min: f32, max: f32) -> Self {
+        Hardiness { min, max, exposure: 0.0 }
+    }
+
+    pub fn survives(&self, temperature: f32) -> bool {
+        (self.min..=self.max).contains(&temperature)
+    }
+}
+
+fn update_ambient(clock: Res<GameClock>, weather: Res<Weather>, mut temperature: ResMut<Temperature>) {
+    // coldest just before dawn, warmest in the afternoon
+    let day = -((clock.time_of_day() - COLDEST_HOUR) / 24.0 * TAU).cos() * DAILY_SWING;
+    temperature.ambient = season_temperature(clock.season) + day + weather.temperature_offset();
+}
+
+// anything built that gives off heat
+fn furnish_heat_sources(
+    mut commands: Commands,
+    query: Query<(Entity, &Structure), Added<Structure>>,
+    registry: Res<StructureRegistry>,
+) {
+    for (entity, structure) in query.iter() {
+        let Some(def) = registry.get(&structure.def).filter(|def| def.heat != 0.0) else {
+            continue;
+        };
+        commands.entity(entity).insert(HeatSource { output: def.heat, radius: def.heat_radius });
+    }
+}
+
+fn update_tiles(
+    mut temperature: ResMut<Temperature>,
+    indoors: Res<Indoors>,
+    heat_sources: Query<(&Transform, &HeatSource)>,
+) {
+    let ambient = temperature.ambient;
+    let mut tiles: HashMap<Pos, f32> = HashMap::new();
+
+    let room = ambient + (SHELTER_TEMPERATURE - ambient) * ROOM_INSULATION;
+    for pos in indoors.tiles.iter() {
+        tiles.insert(*pos, room);
+    }
+
+    for (transform, source) in heat_sources.iter() {
+        let Pos(cx, cy) = Pos::from_translation(transform.translation);
+        for x in cx - source.radius..=cx + source.radius {
+            for y in cy - source.radius..=cy + source.radius {
+                let pos = Pos(x, y);
+                let distance = pos.distance(&Pos(cx, cy)) as f32;
+                if distance > source.radius as f32 {
+                    continue;
+                }
+                let heat = source.output * (1.0 - distance / (source.radius as f32 + 1.0));
+                *tiles.entry(pos).or_insert(ambient) += heat;
+            }
+        }
+    }
+
+    temperature.tiles = tiles;
+}
+
+// builds up while the colonist is too cold (or too hot), wears off otherwise
+fn exposure_change(degrees_outside: f32, dt: f32) -> f32 {
+    if degrees_outside > 0.0 {
+        degrees_outside * EXPOSURE_RATE * dt
+    } else {
+        -EXPOSURE_RECOVERY * dt
+    }
+}
+
+fn expose_colonists(
+    time: Res<Time>,
+    temperature: Res<Temperature>,
+    mut query: Query<(&Transform, &ComfortRange, &mut Exposure, &mut Health, Option<&Name>), With<Character>>,
+) {
+    for (transform, comfort, mut exposure, mut health, name) in query.iter_mut() {
+        let here = temperature.at(Pos::from_translation(transform.translation));
+        let dt = time.delta_seconds();
+
+        let was_harmful = exposure.hypothermia >= EXPOSURE_HARMFUL || exposure.heatstroke >= EXPOSURE_HARMFUL;
+
+        let cold = (comfort.min - here).max(0.0);
+        let hot = (here - comfort.max).max(0.0);
+        exposure.hypothermia = (exposure.hypothermia + exposure_change(cold, dt)).clamp(0.0, 1.0);
+        exposure.heatstroke = (exposure.heatstroke + exposure_change(hot, dt)).clamp(0.0, 1.0);
+
+        if exposure.hypothermia >= EXPOSURE_HARMFUL {
+            health.damage(EXPOSURE_DAMAGE * exposure.hypothermia * dt, DamageCause::Hypothermia);
+        }
+        if exposure.heatstroke >= EXPOSURE_HARMFUL {
+            health.damage(EXPOSURE_DAMAGE * exposure.heatstroke * dt, DamageCause::Heatstroke);
+        }
+
+        let harmful = exposure.hypothermia >= EXPOSURE_HARMFUL || exposure.heatstroke >= EXPOSURE_HARMFUL;
+        if harmful && !was_harmful {
+            if let Some(name) = name {
+                let condition = if exposure.hypothermia >= EXPOSURE_HARMFUL { "hypothermia" } else { "heatstroke" };
+                screen_print!(push, sec: 5.0, "{} is suffering from {}", &name.0, condition);
+            }
+        }
+    }
+}
+
+pub struct TemperaturePlugin;
+
+impl Plugin for TemperaturePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Temperature>()
+            .add_systems(Update, furnish_heat_sources.run_if(in_state(InGame)))
+            .add_systems(Update, update_ambient.run_if(in_state(InGame)))
+            .add_systems(Update, update_tiles.after(update_ambient).run_if(in_state(InGame)))
+            .add_systems(Update, expose_colonists.after(update_tiles).run_if(in_state(InGame)));
+    }
+}
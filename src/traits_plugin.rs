@@ -0,0 +1,122 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde::Deserialize;
+
+const TRAIT_DATA: &str = include_str!("../assets/data/traits.json");
+
+// how many traits a new colonist gets, and how likely each count is
+const TRAIT_COUNT_WEIGHTS: [(usize, u32); 3] = [(0, 1), (1, 3), (2, 2)];
+
+// key in `task_scores` that applies to every work task
+pub const WORK_TASK_KEY: &str = "Work";
+
+fn one() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TraitDef {
+    pub id: String,
+    pub name: String,
+    // relative chance of being picked
+    pub rarity: f32,
+    #[serde(default)]
+    pub incompatible: Vec<String>,
+    // multipliers
+    #[serde(default = "one")]
+    pub hunger_drain: f32,
+    #[serde(default = "one")]
+    pub thirst_drain: f32,
+    #[serde(default = "one")]
+    pub sleep_drain: f32,
+    #[serde(default = "one")]
+    pub move_speed: f32,
+    #[serde(default = "one")]
+    pub work_speed: f32,
+    // flat offsets
+    #[serde(default)]
+    pub mood: f32,
+    // prefers to sleep during the day
+    #[serde(default)]
+    pub nocturnal: bool,
+    // keyed by task name, or WORK_TASK_KEY for all work
+    #[serde(default)]
+    pub task_scores: HashMap<String, f32>,
+}
+
+#[derive(Resource)]
+pub struct TraitRegistry {
+    pub traits: HashMap<String, TraitDef>,
+}
+
+impl TraitRegistry {
+    fn load() -> Self {
+        let defs: Vec<TraitDef> = serde_json::from_str(TRAIT_DATA).expect("invalid trait data");
+        TraitRegistry {
+            traits: defs.into_iter().map(|d| (d.id.clone(), d)).collect(),
+        }
+    }
+
+    pub fn defs<'a>(&'a self, traits: &'a Traits) -> impl Iterator<Item = &'a TraitDef> {
+        traits.ids.iter().filter_map(|id| self.traits.get(id))
+    }
+
+    pub fn product(&self, traits: &Traits, f: impl Fn(&TraitDef) -> f32) -> f32 {
+        self.defs(traits).map(f).product()
+    }
+
+    pub fn sum(&self, traits: &Traits, f: impl Fn(&TraitDef) -> f32) -> f32 {
+        self.defs(traits).map(f).sum()
+    }
+
+    // (trait name, bonus) for every trait that cares about this task
+    pub fn task_scores(&self, traits: &Traits, key: &str) -> Vec<(String, f32)> {
+        self.defs(traits)
+            .filter_map(|d| d.task_scores.get(key).map(|v| (d.name.clone(), *v)))
+            .collect()
+    }
+
+    // picks traits by rarity, never giving a colonist two traits that rule each other out
+    pub fn roll<R: Rng>(&self, rng: &mut R) -> Traits {
+        let counts = WeightedIndex::new(TRAIT_COUNT_WEIGHTS.iter().map(|(_, w)| *w)).unwrap();
+        let count = TRAIT_COUNT_WEIGHTS[counts.sample(rng)].0;
+
+        let mut ids: Vec<String> = vec![];
+        for _ in 0..count {
+            let options: Vec<&TraitDef> = self
+                .traits
+                .values()
+                .filter(|d| !ids.contains(&d.id))
+                .filter(|d| {
+                    ids.iter().all(|id| {
+                        !d.incompatible.contains(id)
+                            && !self.traits.get(id).is_some_and(|o| o.incompatible.contains(&d.id))
+                    })
+                })
+                .collect();
+
+            let Ok(dist) = WeightedIndex::new(options.iter().map(|d| d.rarity)) else {
+                break;
+            };
+            ids.push(options[dist.sample(rng)].id.clone());
+        }
+
+        Traits { ids }
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Traits {
+    pub ids: Vec<String>,
+}
+
+pub struct TraitsPlugin;
+
+impl Plugin for TraitsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TraitRegistry::load());
+    }
+}
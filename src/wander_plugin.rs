@@ -1,8 +1,9 @@
 use std::sync::Mutex;
 
 use bevy::app::App;
-use bevy::ecs::system::CommandQueue;
+use bevy::ecs::system::{CommandQueue, EntityCommands};
 use bevy::prelude::*;
+use bevy::utils::HashSet;
 use bevy::tasks::{AsyncComputeTaskPool, block_on, Task};
 use bevy::tasks::futures_lite::{future};
 use bevy_enum_filter::prelude::*;
@@ -10,9 +11,11 @@ use rand::{Rng, thread_rng};
 
 use crate::AppState;
 use crate::character_plugin::Character;
+use crate::item_plugin::Inventory;
 use crate::pathing::Pos;
+use crate::social_plugin::Listening;
 use crate::tasks::*;
-use crate::world_gen_plugin::{SPRITE_SIZE, TileWeights, WORLD_SIZE_X, WORLD_SIZE_Y};
+use crate::world_gen_plugin::{EditTile, SPRITE_SIZE, TileWeights};
 
 pub struct RandomMovementPlugin;
 
@@ -20,7 +23,13 @@ pub struct RandomMovementPlugin;
 pub struct Wandering;
 
 #[derive(Component)]
-pub struct PathPending;
+pub struct PathPending {
+    pub goal: Pos,
+}
+
+// no path to the goal exists. Whoever asked for the path decides what to do about it
+#[derive(Component)]
+pub struct PathFailed;
 
 #[derive(Component, Debug, PartialEq, Clone, Copy, Reflect)]
 pub struct NeedsPath {
@@ -36,6 +45,23 @@ pub struct Path {
 #[derive(Component)]
 struct ComputeTransform(Task<CommandQueue>);
 
+pub const BASE_MOVE_SPEED: f32 = 200.0;
+
+// world units per second
+#[derive(Component)]
+pub struct MoveSpeed(pub f32);
+
+impl Default for MoveSpeed {
+    fn default() -> Self {
+        MoveSpeed(BASE_MOVE_SPEED)
+    }
+}
+
+// drops whatever path the entity is following or waiting on, e.g. when it switches tasks
+pub fn stop_moving(entity_commands: &mut EntityCommands) {
+    entity_commands.remove::<(Path, NeedsPath, PathPending, PathFailed)>();
+}
+
 fn wander(
     mut commands: Commands,
     query: Query<
@@ -58,7 +84,8 @@ fn assign_path(
     mut commands: Commands,
     query: Query<
         (Entity, &Transform, &NeedsPath),
-        (With<Transform>, With<Wandering>, With<Enum!(AllTasks::Wander)>, Without<Path>, Without<PathPending>),
+        // wait for any abandoned path to finish computing before asking for a new one
+        (With<Transform>, Without<Path>, Without<PathPending>, Without<ComputeTransform>),
     >,
     weights: Res<TileWeights>,
 ) {
@@ -68,8 +95,8 @@ fn assign_path(
 
     for (entity, transform, needs_path) in entities.iter() {
         let entity = entity.clone();
-        commands.entity(entity).insert(PathPending);
-        commands.entity(entity).remove::<NeedsPath>();
+        commands.entity(entity).insert(PathPending { goal: needs_path.pos });
+        commands.entity(entity).remove::<(NeedsPath, PathFailed)>();
 
         let weights = weights.weights.clone();
         let transform = transform.clone();
@@ -78,17 +105,29 @@ fn assign_path(
         let task = thread_pool.spawn(async move {
             let mut command_queue = CommandQueue::default();
 
-            let start = Pos(transform.translation.x as i32 / 32, transform.translation.y as i32 / 32);
+            let start = Pos::from_translation(transform.translation);
 
             let path = pathfinding::prelude::astar(
                 &start,
                 |p| p.successors(&weights),
                 |p| p.distance(&needs_path.pos) / 1,
                 |p| *p == needs_path.pos,
-            ).unwrap();
+            );
 
             command_queue.push(move |world: &mut World| {
-                world.entity_mut(entity).insert(Path { path: path.clone(), index: 0 }).remove::<ComputeTransform>().remove::<PathPending>();
+                // the colonist may have died while the path was being computed
+                if let Some(mut entity) = world.get_entity_mut(entity) {
+                    // nobody is waiting for this path anymore
+                    if !entity.contains::<PathPending>() {
+                        entity.remove::<ComputeTransform>();
+                        return;
+                    }
+                    match path {
+                        Some(path) => entity.insert(Path { path, index: 0 }),
+                        None => entity.insert(PathFailed),
+                    };
+                    entity.remove::<ComputeTransform>().remove::<PathPending>();
+                }
             });
             command_queue
         });
@@ -107,17 +146,47 @@ fn handle_tasks(mut commands: Commands, mut transform_tasks: Query<&mut ComputeT
     }
 }
 
+// something was built or taken down on a tile, so any route through it may be blocked now, or no longer
+// the best one. Paths still being worked out were planned on the old map either way
+fn invalidate_paths(
+    mut commands: Commands,
+    mut edits: EventReader<EditTile>,
+    paths: Query<(Entity, &Path)>,
+    pending: Query<(Entity, &PathPending)>,
+) {
+    let edited: HashSet<Pos> = edits.read().map(|edit| edit.pos).collect();
+    if edited.is_empty() {
+        return;
+    }
+
+    let mut replan: Vec<(Entity, Pos)> = pending.iter().map(|(entity, pending)| (entity, pending.goal)).collect();
+    for (entity, path) in paths.iter() {
+        let remaining = path.path.0.get(path.index..).unwrap_or_default();
+        if !remaining.iter().any(|pos| edited.contains(pos)) {
+            continue;
+        }
+        if let Some(goal) = remaining.last() {
+            replan.push((entity, *goal));
+        }
+    }
+
+    for (entity, goal) in replan {
+        let mut entity_commands = commands.entity(entity);
+        stop_moving(&mut entity_commands);
+        entity_commands.insert(NeedsPath { pos: goal });
+    }
+}
+
 fn follow_path(
     time: Res<Time>,
     commands: Commands,
     mut query: Query<
-        (Entity, &mut Transform, &mut Path),
-        (With<Wandering>, With<Enum!(AllTasks::Wander)>),
+        (Entity, &mut Transform, &mut Path, &MoveSpeed, Option<&Inventory>),
     >,
 ) {
     let commands = Mutex::new(commands);
 
-    query.par_iter_mut().for_each(|(entity, mut transform, mut path)| {
+    query.par_iter_mut().for_each(|(entity, mut transform, mut path, speed, inventory)| {
         let mut next_pos = Vec3::new(
             path.path.0.iter().nth(path.index).unwrap().0 as f32 * SPRITE_SIZE as f32 - (0) as f32 / 2.0,
             path.path.0.iter().nth(path.index).unwrap().1 as f32 * SPRITE_SIZE as f32 - (0) as f32 / 2.0,
@@ -132,8 +201,8 @@ fn follow_path(
                 return;
             }
             next_pos = Vec3::new(
-                path.path.0.iter().nth(path.index).unwrap().0 as f32 * SPRITE_SIZE as f32 - (WORLD_SIZE_X as u32 * SPRITE_SIZE as u32) as f32 / 2.0,
-                path.path.0.iter().nth(0).unwrap().1 as f32 * SPRITE_SIZE as f32 - (WORLD_SIZE_Y as u32 * SPRITE_SIZE as u32) as f32 / 2.0,
+                path.path.0.iter().nth(path.index).unwrap().0 as f32 * SPRITE_SIZE as f32,
+                path.path.0.iter().nth(path.index).unwrap().1 as f32 * SPRITE_SIZE as f32,
                 transform.translation.z,
             );
         }
@@ -142,8 +211,10 @@ fn follow_path(
         if let Some(d) = dir.try_normalize() {
             dir = d;
         }
-        transform.translation.x += time.delta_seconds() * dir.x * 200.0;
-        transform.translation.y += time.delta_seconds() * dir.y * 200.0;
+        // heavy loads slow the carrier down
+        let speed = speed.0 * inventory.map_or(1.0, Inventory::speed_factor);
+        transform.translation.x += time.delta_seconds() * dir.x * speed;
+        transform.translation.y += time.delta_seconds() * dir.y * speed;
     });
 }
 
@@ -151,7 +222,16 @@ fn set_wander_goal(
     mut commands: Commands,
     query: Query<
         Entity,
-        (With<Transform>, With<Wandering>, With<Enum!(AllTasks::Wander)>, Without<Path>, Without<NeedsPath>, Without<PathPending>),
+        (
+            With<Transform>,
+            With<Wandering>,
+            With<Enum!(AllTasks::Wander)>,
+            Without<Path>,
+            Without<NeedsPath>,
+            Without<PathPending>,
+            // stopped to hear someone out
+            Without<Listening>,
+        ),
     >,
 ) {
     for entity in query.iter() {
@@ -165,6 +245,7 @@ impl Plugin for RandomMovementPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Update, wander.run_if(in_state(AppState::InGame)))
             .add_systems(Update, assign_path.run_if(in_state(AppState::InGame)))
+            .add_systems(Update, invalidate_paths.run_if(in_state(AppState::InGame)))
             .add_systems(Update, follow_path.run_if(in_state(AppState::InGame)))
             .add_systems(Update, set_wander_goal.run_if(in_state(AppState::InGame)))
             .add_systems(Update, handle_tasks);
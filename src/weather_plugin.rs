@@ -0,0 +1,187 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy_debug_text_overlay::screen_print;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter};
+
+use crate::AppState::InGame;
+use crate::clock_plugin::{GameClock, Season, MINUTES_PER_SECOND};
+use crate::temperature_plugin::Temperature;
+use crate::world_gen_plugin::{TerrainKind, TerrainMap, TileWeights, GRASS_COST};
+
+// how long a spell of weather lasts, in game minutes
+const MIN_WEATHER_DURATION: f32 = 12.0 * 60.0;
+const MAX_WEATHER_DURATION: f32 = 3.0 * 24.0 * 60.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, EnumIter, AsRefStr)]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Rain,
+    Drought,
+    Frost,
+    HeatWave,
+}
+
+impl WeatherKind {
+    // relative chance of this weather coming up in the given season
+    fn chance(&self, season: Season) -> u32 {
+        match (self, season) {
+            (WeatherKind::Clear, _) => 5,
+            (WeatherKind::Rain, Season::Spring | Season::Autumn) => 4,
+            (WeatherKind::Rain, Season::Summer) => 2,
+            (WeatherKind::Rain, Season::Winter) => 1,
+            (WeatherKind::Drought, Season::Summer) => 2,
+            (WeatherKind::Drought, Season::Autumn) => 1,
+            (WeatherKind::Frost, Season::Winter) => 5,
+            (WeatherKind::Frost, Season::Spring | Season::Autumn) => 1,
+            (WeatherKind::HeatWave, Season::Summer) => 2,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Weather {
+    pub kind: WeatherKind,
+    // game minutes until the weather changes
+    pub remaining: f32,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Weather {
+            kind: WeatherKind::Clear,
+            remaining: MIN_WEATHER_DURATION,
+        }
+    }
+}
+
+impl Weather {
+    pub fn growth_multiplier(&self, season: Season) -> f32 {
+        let season_rate = match season {
+            Season::Spring => 1.2,
+            Season::Summer => 1.0,
+            Season::Autumn => 0.6,
+            Season::Winter => 0.1,
+        };
+        let weather_rate = match self.kind {
+            WeatherKind::Clear => 1.0,
+            WeatherKind::Rain => 1.3,
+            WeatherKind::Drought => 0.4,
+            WeatherKind::Frost => 0.2,
+            WeatherKind::HeatWave => 0.6,
+        };
+        season_rate * weather_rate
+    }
+
+    pub fn thirst_multiplier(&self) -> f32 {
+        match self.kind {
+            WeatherKind::Rain => 0.9,
+            WeatherKind::Drought => 1.3,
+            WeatherKind::HeatWave => 1.6,
+            _ => 1.0,
+        }
+    }
+
+    // degrees added to the ambient temperature
+    pub fn temperature_offset(&self) -> f32 {
+        match self.kind {
+            WeatherKind::Clear => 0.0,
+            WeatherKind::Rain => -2.0,
+            WeatherKind::Drought => 3.0,
+            WeatherKind::Frost => -10.0,
+            WeatherKind::HeatWave => 10.0,
+        }
+    }
+
+    // for colonists who are outside
+    pub fn outdoor_mood(&self) -> f32 {
+        match self.kind {
+            WeatherKind::Clear => 1.0,
+            WeatherKind::Rain => -3.0,
+            WeatherKind::Drought => -1.0,
+            WeatherKind::Frost => -4.0,
+            WeatherKind::HeatWave => -4.0,
+        }
+    }
+
+    // path cost of walking over a mud tile. Rain makes it a swamp, frozen or dried out mud is like grass
+    pub fn mud_cost(&self) -> i32 {
+        match self.kind {
+            WeatherKind::Clear => 4,
+            WeatherKind::Rain => 10,
+            _ => GRASS_COST,
+        }
+    }
+}
+
+#[derive(Component)]
+struct WeatherText;
+
+fn change_weather(time: Res<Time>, clock: Res<GameClock>, mut weather: ResMut<Weather>) {
+    weather.remaining -= time.delta_seconds() * MINUTES_PER_SECOND;
+    if weather.remaining > 0.0 {
+        return;
+    }
+
+    let mut rng = thread_rng();
+    let kinds: Vec<WeatherKind> = WeatherKind::iter().collect();
+    let dist = WeightedIndex::new(kinds.iter().map(|k| k.chance(clock.season))).unwrap();
+    let kind = kinds[dist.sample(&mut rng)];
+
+    if kind != weather.kind {
+        screen_print!(push, sec: 5.0, "The weather turns: {}", kind.as_ref());
+    }
+    weather.kind = kind;
+    weather.remaining = rng.gen_range(MIN_WEATHER_DURATION..MAX_WEATHER_DURATION);
+}
+
+// the muddy tiles get easier or harder to cross with the weather
+fn update_mud_weights(weather: Res<Weather>, terrain: Res<TerrainMap>, mut weights: ResMut<TileWeights>) {
+    if !weather.is_changed() && !terrain.is_added() {
+        return;
+    }
+
+    for (pos, kind) in terrain.tiles.iter() {
+        if *kind == TerrainKind::Mud {
+            weights.refresh(*pos, &terrain, &weather);
+        }
+    }
+}
+
+fn spawn_weather_text(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle { font_size: 20.0, ..default() }).with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(32.0),
+            right: Val::Px(8.0),
+            ..default()
+        }),
+        WeatherText,
+    ));
+}
+
+fn update_weather_text(
+    weather: Res<Weather>,
+    temperature: Res<Temperature>,
+    mut query: Query<&mut Text, With<WeatherText>>,
+) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("{}, {:.0}°C", weather.kind.as_ref(), temperature.ambient);
+    }
+}
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Weather>()
+            .add_systems(OnEnter(InGame), spawn_weather_text)
+            .add_systems(Update, change_weather.run_if(in_state(InGame)))
+            .add_systems(Update, update_mud_weights.run_if(in_state(InGame)))
+            .add_systems(Update, update_weather_text.run_if(in_state(InGame)));
+    }
+}
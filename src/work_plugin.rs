@@ -0,0 +1,111 @@
+use bevy::app::{App, Plugin};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter};
+
+use crate::AppState::InGame;
+use crate::character_plugin::Character;
+use crate::name_plugin::Name;
+use crate::tasks::AllTasks;
+
+// 1 is the most important, MAX_PRIORITY the least. 0 means the colonist never does this work
+pub const MAX_PRIORITY: u8 = 4;
+const DEFAULT_PRIORITY: u8 = 3;
+
+// work scores start between Wander (1.0) and the needs (8.0+). The Work schedule slot and traits can
+// lift top-priority work as far as a lonely colonist's Socialize, but never to an empty stomach or bed
+const WORK_SCORE_BASE: f32 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, AsRefStr)]
+pub enum WorkType {
+    Hauling,
+    Growing,
+    Building,
+    Mining,
+    Cooking,
+}
+
+#[derive(Component)]
+pub struct WorkPriorities {
+    pub priorities: HashMap<WorkType, u8>,
+}
+
+impl Default for WorkPriorities {
+    fn default() -> Self {
+        WorkPriorities {
+            priorities: WorkType::iter().map(|w| (w, DEFAULT_PRIORITY)).collect(),
+        }
+    }
+}
+
+impl WorkPriorities {
+    pub fn get(&self, work_type: WorkType) -> u8 {
+        *self.priorities.get(&work_type).unwrap_or(&0)
+    }
+
+    // cycles 1 -> 2 -> ... -> MAX_PRIORITY -> disabled -> 1
+    pub fn cycle(&mut self, work_type: WorkType) {
+        let next = (self.get(work_type) + 1) % (MAX_PRIORITY + 1);
+        self.priorities.insert(work_type, next);
+    }
+
+    // None if the colonist has this work disabled. Ties between equal priorities are broken by
+    // the column order of `WorkType`, like reading the work grid left to right
+    pub fn score(&self, work_type: WorkType) -> Option<f32> {
+        match self.get(work_type) {
+            0 => None,
+            priority => Some(WORK_SCORE_BASE - priority as f32 - work_type as usize as f32 * 0.1),
+        }
+    }
+}
+
+// Filled in by the systems that hand out work. The scorer only offers a work type to a colonist
+// if there is something to do for it
+#[derive(Resource, Default)]
+pub struct AvailableWork {
+    pub jobs: HashMap<WorkType, Vec<AllTasks>>,
+}
+
+fn work_priorities_ui(
+    mut contexts: EguiContexts,
+    mut query: Query<(&Name, &mut WorkPriorities), With<Character>>,
+) {
+    egui::Window::new("Work priorities")
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("work_priorities_grid")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("");
+                    for work_type in WorkType::iter() {
+                        ui.label(work_type.as_ref());
+                    }
+                    ui.end_row();
+
+                    for (name, mut priorities) in query.iter_mut() {
+                        ui.label(&name.0);
+                        for work_type in WorkType::iter() {
+                            let text = match priorities.get(work_type) {
+                                0 => "".to_string(),
+                                p => p.to_string(),
+                            };
+                            if ui.add(egui::Button::new(text).min_size(egui::vec2(24.0, 18.0))).clicked() {
+                                priorities.cycle(work_type);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+pub struct WorkPlugin;
+
+impl Plugin for WorkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AvailableWork>()
+            .add_systems(Update, work_priorities_ui.run_if(in_state(InGame)));
+    }
+}
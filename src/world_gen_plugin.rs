@@ -3,19 +3,29 @@ use bevy::asset::LoadedFolder;
 use bevy::math::{uvec2, vec2};
 use bevy::prelude::*;
 use bevy::render::texture::ImageSampler;
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
 use bevy_fast_tilemap::*;
 use rand::distributions::WeightedIndex;
 use rand::prelude::*;
+use serde::Deserialize;
 
 use crate::{AppState, TerrainFolder};
 use crate::growth_plugin::Growth;
-use crate::pathing::Pos;
+use crate::pathing::{Pos, IMPASSABLE};
+use crate::weather_plugin::Weather;
 
 pub const SPRITE_SIZE: i32 = 32;
 pub const WORLD_SIZE_X: i32 = 512;
 pub const WORLD_SIZE_Y: i32 = 512;
 
+// path costs. Bare ground costs twice as much as a floor, so floors are worth walking round to
+pub const GRASS_COST: i32 = 2;
+const FLOOR_COST: i32 = 1;
+const DOOR_COST: i32 = 6;
+
+// the most tiles walls can shut in and still count as a room. Anything bigger is as good as outdoors
+const MAX_ROOM_SIZE: usize = 400;
+
 #[allow(unused)]
 #[derive(Component)]
 pub struct Terrain;
@@ -29,6 +39,148 @@ pub struct TileWeights {
     pub weights: HashMap::<Pos, i32>,
 }
 
+impl TileWeights {
+    // works out the path cost of a tile from the ground, the weather and whatever is built there. All
+    // weight changes go through here, so they never disagree with each other
+    pub fn refresh(&mut self, pos: Pos, terrain: &TerrainMap, weather: &Weather) {
+        let Some(kind) = terrain.tiles.get(&pos) else {
+            return;
+        };
+        let cost = match terrain.structures.get(&pos) {
+            Some(StructureKind::Wall) => IMPASSABLE,
+            Some(StructureKind::Floor) => FLOOR_COST,
+            Some(StructureKind::Door) => DOOR_COST,
+            // furniture is kept in its own layer, and doesn't get in the way
+            Some(StructureKind::Bed | StructureKind::Workbench | StructureKind::Heater) | None => match kind {
+                TerrainKind::Grass => GRASS_COST,
+                TerrainKind::Mud => weather.mud_cost(),
+            },
+        };
+        self.weights.insert(pos, cost);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum TerrainKind {
+    Grass,
+    Mud,
+}
+
+impl TerrainKind {
+    // how well plants take root here
+    pub fn fertility(&self) -> f32 {
+        match self {
+            TerrainKind::Grass => 1.0,
+            TerrainKind::Mud => 0.5,
+        }
+    }
+}
+
+// something built on a tile, which decides how easy it is to cross instead of the ground
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum StructureKind {
+    Wall,
+    Floor,
+    Door,
+    Bed,
+    Workbench,
+    Heater,
+}
+
+impl StructureKind {
+    // stands on the ground or on a floor, kept apart from what it stands on
+    pub fn is_furniture(&self) -> bool {
+        matches!(self, StructureKind::Bed | StructureKind::Workbench | StructureKind::Heater)
+    }
+
+    // furniture can go on a floor, anything else needs the tile to itself
+    pub fn fits_with(&self, other: StructureKind) -> bool {
+        match (*self, other) {
+            (StructureKind::Floor, other) | (other, StructureKind::Floor) => other.is_furniture(),
+            _ => false,
+        }
+    }
+}
+
+// what the ground is made of and what's built on it, keyed the same way as TileWeights
+#[derive(Resource)]
+pub struct TerrainMap {
+    pub tiles: HashMap<Pos, TerrainKind>,
+    // walls, floors and doors
+    pub structures: HashMap<Pos, StructureKind>,
+    // beds, workbenches and the like, which can stand on a floor
+    pub furniture: HashMap<Pos, StructureKind>,
+}
+
+impl TerrainMap {
+    // everything built on a tile, floor first
+    pub fn built(&self, pos: Pos) -> impl Iterator<Item = StructureKind> + '_ {
+        self.structures.get(&pos).into_iter().chain(self.furniture.get(&pos)).copied()
+    }
+}
+
+// tiles shut in by walls and doors, out of the sun and the rain
+#[derive(Resource, Default)]
+pub struct Indoors {
+    pub tiles: HashSet<Pos>,
+}
+
+impl Indoors {
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.tiles.contains(&pos)
+    }
+}
+
+// fills out from every tile next to a wall or door. Whatever can't reach the edge of the map, or
+// more than MAX_ROOM_SIZE tiles, is a room
+fn enclosed_tiles(terrain: &TerrainMap) -> HashSet<Pos> {
+    let blocks = |pos: &Pos| matches!(terrain.structures.get(pos), Some(StructureKind::Wall | StructureKind::Door));
+    let neighbours = |Pos(x, y): Pos| [Pos(x + 1, y), Pos(x - 1, y), Pos(x, y + 1), Pos(x, y - 1)];
+
+    let mut indoors = HashSet::new();
+    let mut outdoors = HashSet::new();
+    for wall in terrain.structures.keys().filter(|pos| blocks(pos)) {
+        for start in neighbours(*wall) {
+            if blocks(&start) || indoors.contains(&start) || outdoors.contains(&start) {
+                continue;
+            }
+            let mut region = HashSet::from([start]);
+            let mut open = vec![start];
+            let mut escaped = false;
+            while let Some(pos) = open.pop() {
+                if region.len() > MAX_ROOM_SIZE || !terrain.tiles.contains_key(&pos) {
+                    escaped = true;
+                    break;
+                }
+                for next in neighbours(pos) {
+                    if !blocks(&next) && region.insert(next) {
+                        open.push(next);
+                    }
+                }
+            }
+            if escaped {
+                outdoors.extend(region);
+            } else {
+                indoors.extend(region);
+            }
+        }
+    }
+    indoors
+}
+
+// builds something on a tile, or takes it down again
+#[derive(Event)]
+pub struct EditTile {
+    pub pos: Pos,
+    pub structure: StructureKind,
+    pub removed: bool,
+}
+
+// the map is centered on the origin, so tile (0, 0) of the map is in the bottom left corner of the world
+pub fn map_to_pos(x: u32, y: u32) -> Pos {
+    Pos(x as i32 - WORLD_SIZE_X / 2, y as i32 - WORLD_SIZE_Y / 2)
+}
+
 pub struct WorldGenPlugin;
 
 pub(crate) fn create_texture_atlas(
@@ -76,13 +228,22 @@ fn create_world(
     // All the texture atlas stuff is from: https://github.com/bevyengine/bevy/blob/main/examples/2d/texture_atlas.rs
     let loaded_folder = loaded_folders.get(&terrain_sprites_handles.0).unwrap();
 
-    let (_, linear_texture) = create_texture_atlas(
+    let (texture_atlas_layout, linear_texture) = create_texture_atlas(
         loaded_folder,
         None,
         Some(ImageSampler::nearest()),
         &mut textures,
     );
 
+    // atlas indices of the mud sprites, everything else is grass
+    let mud_indices: Vec<u32> = loaded_folder
+        .handles
+        .iter()
+        .filter(|h| h.path().is_some_and(|p| p.to_string().contains("mud")))
+        .filter_map(|h| texture_atlas_layout.get_texture_index(h.id().typed_unchecked::<Image>()))
+        .map(|i| i as u32)
+        .collect();
+
     const NUM_TERRAIN_TILES: usize = 7;
     let mut weights = [1; NUM_TERRAIN_TILES];
 
@@ -92,6 +253,7 @@ fn create_world(
     let dist = WeightedIndex::new(&weights).unwrap();
 
     let mut hashmap = HashMap::new();
+    let mut terrain = HashMap::new();
 
     let map = Map::builder(
         uvec2(WORLD_SIZE_X as u32, WORLD_SIZE_Y as u32),
@@ -102,9 +264,12 @@ fn create_world(
             // Initialize using a closure
             for y in 0..m.size().y {
                 for x in 0..m.size().y {
-                    m.set(x, y, dist.sample(&mut rand) as u32);
+                    let index = dist.sample(&mut rand) as u32;
+                    m.set(x, y, index);
                     //hashmap.insert(Pos(x as i32, y as i32), rand.gen_range(1..255));
-                    hashmap.insert(Pos(x as i32, y as i32), 1);
+                    hashmap.insert(map_to_pos(x, y), GRASS_COST);
+                    let kind = if mud_indices.contains(&index) { TerrainKind::Mud } else { TerrainKind::Grass };
+                    terrain.insert(map_to_pos(x, y), kind);
                 }
             }
         });
@@ -115,13 +280,45 @@ fn create_world(
     });
 
     commands.insert_resource(TileWeights { weights: hashmap });
+    commands.insert_resource(TerrainMap {
+        tiles: terrain,
+        structures: HashMap::new(),
+        furniture: HashMap::new(),
+    });
 
     next_state.set(AppState::InGame);
 }
 
+pub fn edit_tiles(
+    mut events: EventReader<EditTile>,
+    mut terrain: ResMut<TerrainMap>,
+    mut weights: ResMut<TileWeights>,
+    mut indoors: ResMut<Indoors>,
+    weather: Res<Weather>,
+) {
+    let mut edited = false;
+    for event in events.read() {
+        let layer = if event.structure.is_furniture() { &mut terrain.furniture } else { &mut terrain.structures };
+        if event.removed {
+            layer.remove(&event.pos);
+        } else {
+            layer.insert(event.pos, event.structure);
+        }
+        weights.refresh(event.pos, &terrain, &weather);
+        edited = true;
+    }
+    // a wall going up or coming down can open or close off a whole room
+    if edited {
+        indoors.tiles = enclosed_tiles(&terrain);
+    }
+}
+
 impl Plugin for WorldGenPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Growth>()
-            .add_systems(OnEnter(AppState::CreateWorld), create_world);
+            .add_event::<EditTile>()
+            .init_resource::<Indoors>()
+            .add_systems(OnEnter(AppState::CreateWorld), create_world)
+            .add_systems(Update, edit_tiles.run_if(in_state(AppState::InGame)));
     }
 }